@@ -0,0 +1,371 @@
+//! Asynchronous packet transmission and reception.
+//!
+//! [`AsyncCc1101`] owns the radio together with an input pin connected to GDO0 or GDO2, and awaits
+//! the packet handling signals on that pin using [`Wait`] instead of polling the chip over SPI.
+//! SPI accesses remain blocking, they are short compared to the time spent on air.
+//!
+//! The pin is sampled with [`InputPin`] before waiting, so that a packet sent or received before
+//! the wait started is not missed.
+//!
+//! The futures returned by [`AsyncCc1101::transmit`] and [`AsyncCc1101::receive`] are cancel-safe:
+//! dropping one before it completes strobes the chip to IDLE and flushes both FIFOs, without
+//! waiting for the chip.
+
+use crate::{Cc1101, Error, GdoCfg, GdoPin, MachineState, Status, StatusByte, UserError, RXBYTES};
+use embedded_hal_async::digital::Wait;
+use hal::digital::{self, InputPin, PinState};
+use hal::spi::SpiDevice;
+
+/// Strobes the chip to IDLE and flushes the FIFOs when dropped, unless disarmed.
+struct Abort<'a, SPI: SpiDevice<u8>, O: FnMut(StatusByte)> {
+    radio: &'a mut Cc1101<SPI, O>,
+    armed: bool,
+}
+
+impl<'a, SPI: SpiDevice<u8>, O: FnMut(StatusByte)> Abort<'a, SPI, O> {
+    fn new(radio: &'a mut Cc1101<SPI, O>) -> Self {
+        Abort {
+            radio,
+            armed: true,
+        }
+    }
+
+    fn disarm(&mut self) {
+        self.armed = false;
+    }
+}
+
+impl<SPI: SpiDevice<u8>, O: FnMut(StatusByte)> Drop for Abort<'_, SPI, O> {
+    fn drop(&mut self) {
+        if self.armed {
+            // Errors cannot be reported from drop, the next operation will report a broken bus.
+            // Strobes only, polling the chip from drop could block the executor.
+            let _ = self.radio.exit_rx_tx();
+            let _ = self.radio.flush_rx_fifo_buffer();
+            let _ = self.radio.flush_tx_fifo_buffer();
+        }
+    }
+}
+
+/// Radio driver awaiting packet handling signals on a GDO pin.
+pub struct AsyncCc1101<SPI, GDO, O = fn(StatusByte)> {
+    radio: Cc1101<SPI, O>,
+    gdo: GDO,
+    config: GdoCfg,
+}
+
+impl<SPI, SpiE, GDO, O> AsyncCc1101<SPI, GDO, O>
+where
+    SPI: SpiDevice<u8, Error = SpiE>,
+    GDO: Wait + InputPin,
+    O: FnMut(StatusByte),
+{
+    /// Take ownership of the radio and the pin connected to `pin`, and route `config` to it,
+    /// active high.
+    ///
+    /// `config` must be [`GdoCfg::SYNC_WORD`] or [`GdoCfg::RX_FIFO_FILLED_END_OF_PKT`]. The latter
+    /// only supports reception, with the RX FIFO threshold set above the largest packet so that
+    /// the pin asserts at the end of the packet.
+    pub fn new(
+        mut radio: Cc1101<SPI, O>,
+        gdo: GDO,
+        pin: GdoPin,
+        config: GdoCfg,
+    ) -> Result<Self, Error<SpiE>> {
+        match config {
+            GdoCfg::SYNC_WORD | GdoCfg::RX_FIFO_FILLED_END_OF_PKT => {}
+            _ => return Err(Error::UserInputError(UserError::UnsupportedGdoConfig(config))),
+        }
+
+        radio.set_gdo_config(pin, config)?;
+        radio.set_gdo_active_state(pin, PinState::High)?;
+
+        Ok(AsyncCc1101 {
+            radio,
+            gdo,
+            config,
+        })
+    }
+
+    /// Release the radio and the pin.
+    pub fn release(self) -> (Cc1101<SPI, O>, GDO) {
+        (self.radio, self.gdo)
+    }
+
+    /// Access the radio, e.g. to change the configuration.
+    pub fn radio(&mut self) -> &mut Cc1101<SPI, O> {
+        &mut self.radio
+    }
+
+    /// Send a packet, waiting for the end of the packet on the GDO pin.
+    /// The length field is derived from `data`, and the address field is required when address
+    /// filtering is enabled. The packet must fit into the TX FIFO.
+    /// Transmitting from RX with clear channel assessment enabled fails with `Error::ChannelBusy`
+    /// when the channel is busy, as in [`Cc1101::transmit`].
+    ///
+    /// Requires [`GdoCfg::SYNC_WORD`].
+    pub async fn transmit(
+        &mut self,
+        address: Option<u8>,
+        data: &mut [u8],
+    ) -> Result<(), Error<SpiE>> {
+        if self.config != GdoCfg::SYNC_WORD {
+            return Err(Error::UserInputError(UserError::UnsupportedGdoConfig(self.config)));
+        }
+
+        let gdo = &mut self.gdo;
+        let mut guard = Abort::new(&mut self.radio);
+
+        let mut length = Some((data.len() + guard.radio.0.address_field as usize) as u8);
+        let mut address = address;
+        guard.radio.write_data(&mut length, &mut address, data)?;
+        guard.radio.enable_tx()?;
+
+        // The pin asserts when the sync word has been sent and de-asserts at the end of the
+        // packet. It never asserts when STX was ignored because the channel is busy, and does not
+        // assert again when the packet has already been sent. The pin is sampled before the
+        // chip, so a low pin with data left in the TX FIFO means the packet has not ended yet.
+        loop {
+            if gdo.is_high().map_err(pin_error)? {
+                gdo.wait_for_low().await.map_err(pin_error)?;
+                break;
+            }
+            match (guard.radio.get_machine_state()?, guard.radio.get_tx_bytes()) {
+                (MachineState::RX, Ok(n)) if n > 0 => {
+                    guard.disarm();
+                    return guard.radio.abort_busy_transmission();
+                }
+                (_, Ok(0)) | (_, Err(_)) => break,
+                _ => gdo.wait_for_high().await.map_err(pin_error)?,
+            }
+        }
+
+        match guard.radio.get_tx_bytes() {
+            Ok(_) => {
+                guard.disarm();
+                Ok(())
+            }
+            Err(Error::TxUnderflow) => {
+                guard.disarm();
+                let txoff_mode = guard.radio.get_txoff_mode()?;
+                guard.radio.recover_tx_underflow(txoff_mode)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Receive a packet, waiting for it on the GDO pin. `buf` is sized like for
+    /// [`Cc1101::receive`], returns the packet length.
+    ///
+    /// Packets discarded by the chip, e.g. by address filtering or CRC autoflush, are skipped.
+    pub async fn receive(&mut self, addr: &mut u8, buf: &mut [u8]) -> Result<u8, Error<SpiE>> {
+        let gdo = &mut self.gdo;
+        let config = self.config;
+        let mut guard = Abort::new(&mut self.radio);
+
+        guard.radio.enable_rx()?;
+
+        loop {
+            match config {
+                // Asserts at the end of the packet, until the RX FIFO is read
+                GdoCfg::RX_FIFO_FILLED_END_OF_PKT => {
+                    gdo.wait_for_high().await.map_err(pin_error)?
+                }
+                // Asserts on sync word, de-asserts at the end of the packet or when the packet is
+                // discarded. Sampled before the chip, so that received bytes belong to a packet
+                // that has ended.
+                _ if gdo.is_high().map_err(pin_error)? => {
+                    gdo.wait_for_low().await.map_err(pin_error)?;
+                    continue;
+                }
+                _ => {}
+            }
+
+            let rxbytes = RXBYTES(guard.radio.0.read_status_stable(Status::RXBYTES)?);
+            if rxbytes.rxfifo_overflow() != 0 {
+                guard.disarm();
+                return guard.radio.recover_rx_overflow(addr, buf, true);
+            }
+            if rxbytes.num_rxbytes() > 0 {
+                break;
+            }
+            if config == GdoCfg::SYNC_WORD {
+                // Returns at once when a packet started since the pin was sampled
+                gdo.wait_for_high().await.map_err(pin_error)?;
+            }
+        }
+
+        let mut length: Option<u8> = Some(0);
+        let mut address: Option<u8> = Some(0);
+        let mut rssi: Option<i16> = Some(0);
+        let mut lqi: Option<u8> = Some(0);
+        guard.radio.read_data(&mut length, &mut address, &mut rssi, &mut lqi, buf)?;
+        guard.disarm();
+
+        *addr = address.unwrap();
+        match lqi {
+            Some(lqi) if guard.radio.0.rx_status_fields && (lqi >> 7) != 1 => {
+                Err(Error::CrcMismatch)
+            }
+            _ => Ok(length.unwrap()),
+        }
+    }
+}
+
+fn pin_error<SpiE, E: digital::Error>(e: E) -> Error<SpiE> {
+    Error::Gdo(e.kind())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::{radio, Chip};
+    use crate::{Command, PacketLength};
+    use core::cell::Cell;
+    use core::convert::Infallible;
+    use core::future::{pending, Future};
+    use core::pin::pin;
+    use core::task::{Context, Poll, Waker};
+    use std::boxed::Box;
+    use std::collections::VecDeque;
+    use std::vec::Vec;
+
+    /// GDO pin sampled at the given levels, then low. Waits complete at once, unless hanging.
+    #[derive(Default)]
+    struct Gdo {
+        levels: VecDeque<bool>,
+        hang: bool,
+        waits: Vec<&'static str>,
+    }
+
+    impl Gdo {
+        async fn wait(&mut self, name: &'static str) -> Result<(), Infallible> {
+            self.waits.push(name);
+            if self.hang {
+                pending::<()>().await;
+            }
+            Ok(())
+        }
+    }
+
+    impl digital::ErrorType for Gdo {
+        type Error = Infallible;
+    }
+
+    impl InputPin for Gdo {
+        fn is_high(&mut self) -> Result<bool, Infallible> {
+            Ok(self.levels.pop_front().unwrap_or(false))
+        }
+
+        fn is_low(&mut self) -> Result<bool, Infallible> {
+            self.is_high().map(|high| !high)
+        }
+    }
+
+    impl Wait for Gdo {
+        async fn wait_for_high(&mut self) -> Result<(), Infallible> {
+            self.wait("high").await
+        }
+
+        async fn wait_for_low(&mut self) -> Result<(), Infallible> {
+            self.wait("low").await
+        }
+
+        async fn wait_for_rising_edge(&mut self) -> Result<(), Infallible> {
+            self.wait("rising").await
+        }
+
+        async fn wait_for_falling_edge(&mut self) -> Result<(), Infallible> {
+            self.wait("falling").await
+        }
+
+        async fn wait_for_any_edge(&mut self) -> Result<(), Infallible> {
+            self.wait("any").await
+        }
+    }
+
+    fn poll_once<F: Future>(future: F) -> Poll<F::Output> {
+        pin!(future).as_mut().poll(&mut Context::from_waker(Waker::noop()))
+    }
+
+    fn async_radio<O: FnMut(StatusByte)>(
+        mut radio: Cc1101<Chip, O>,
+        gdo: Gdo,
+    ) -> AsyncCc1101<Chip, Gdo, O> {
+        radio.set_packet_length(PacketLength::Variable(61)).unwrap();
+        AsyncCc1101::new(radio, gdo, GdoPin::Gdo0, GdoCfg::SYNC_WORD).unwrap()
+    }
+
+    #[test]
+    fn test_transmit_sent_before_wait() {
+        let mut radio = async_radio(radio(), Gdo::default());
+        assert_eq!(poll_once(radio.transmit(None, &mut [1, 2, 3])), Poll::Ready(Ok(())));
+        let (chip, gdo) = (&radio.radio.0.spi, &radio.gdo);
+        assert_eq!(chip.sent, [[3, 1, 2, 3]]);
+        assert_eq!(gdo.waits, [] as [&str; 0]);
+    }
+
+    #[test]
+    fn test_transmit_in_progress() {
+        let gdo = Gdo {
+            levels: [true].into(),
+            ..Gdo::default()
+        };
+        let mut radio = async_radio(radio(), gdo);
+        assert_eq!(poll_once(radio.transmit(None, &mut [1, 2, 3])), Poll::Ready(Ok(())));
+        assert_eq!(radio.gdo.waits, ["low"]);
+    }
+
+    #[test]
+    fn test_receive_before_wait() {
+        let mut radio = async_radio(radio(), Gdo::default());
+        radio.radio.0.spi.receive(&[3, 1, 2, 3, 0x40, 0x80]);
+        let mut buf = [0; 5];
+        assert_eq!(poll_once(radio.receive(&mut 0, &mut buf)), Poll::Ready(Ok(3)));
+        assert_eq!(buf[..3], [1, 2, 3]);
+        assert_eq!(radio.gdo.waits, [] as [&str; 0]);
+    }
+
+    #[test]
+    fn test_receive_in_progress() {
+        let gdo = Gdo {
+            levels: [true, false].into(),
+            ..Gdo::default()
+        };
+        let mut radio = async_radio(radio(), gdo);
+        radio.radio.0.spi.receive(&[3, 1, 2, 3, 0x40, 0x80]);
+        let mut buf = [0; 5];
+        assert_eq!(poll_once(radio.receive(&mut 0, &mut buf)), Poll::Ready(Ok(3)));
+        assert_eq!(radio.gdo.waits, ["low"]);
+    }
+
+    #[test]
+    fn test_cancel() {
+        let transactions = Cell::new(0);
+        let radio =
+            Cc1101::with_status_observer(Chip::new(), |_| transactions.set(transactions.get() + 1))
+                .unwrap();
+        let gdo = Gdo {
+            hang: true,
+            ..Gdo::default()
+        };
+        let mut radio = async_radio(radio, gdo);
+
+        let (mut addr, mut buf) = (0, [0; 5]);
+        let mut future = Box::pin(radio.receive(&mut addr, &mut buf));
+        let poll = future.as_mut().poll(&mut Context::from_waker(Waker::noop()));
+        assert_eq!(poll, Poll::Pending);
+        let before = transactions.get();
+        drop(future);
+
+        // Three strobes, without polling the chip
+        assert_eq!(transactions.get() - before, 3);
+        let chip = &radio.radio.0.spi;
+        assert_eq!(
+            chip.strobes[chip.strobes.len() - 3..],
+            [Command::SIDLE, Command::SFRX, Command::SFTX]
+        );
+        assert_eq!(chip.illegal, []);
+        assert_eq!(radio.gdo.waits, ["high"]);
+    }
+}
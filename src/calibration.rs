@@ -0,0 +1,198 @@
+//! Frequency synthesizer calibration table for fast channel hopping.
+//!
+//! Calibrating the frequency synthesizer takes about 720 µs. The [`CalibrationTable`] calibrates
+//! each channel of a list once with SCAL and stores the FSCAL3, FSCAL2 and FSCAL1 results. A hop
+//! then only writes CHANNR and the stored results, with automatic calibration disabled.
+//!
+//! The calibration results depend on the chip temperature. The table is recalibrated when the
+//! temperature reported by [`CalibrationTable::update_temperature`] drifted too far from the
+//! temperature at calibration time.
+
+use crate::{AutoCalibration, Cc1101, Error, FsCal, MachineState, StatusByte, UserError};
+use hal::spi::SpiDevice;
+
+/// Calibration results for a list of `N` channels.
+pub struct CalibrationTable<const N: usize> {
+    channels: [u8; N],
+    results: [FsCal; N],
+    temperature: Option<i16>,
+    max_drift: u16,
+}
+
+impl<const N: usize> CalibrationTable<N> {
+    /// Calibrate each channel and store the results. The radio is left in IDLE, on the last
+    /// channel, with automatic calibration disabled.
+    ///
+    /// `temperature` is the chip temperature (in degrees Celsius) if known, `max_drift` the change
+    /// in temperature (in degrees Celsius) that triggers a recalibration.
+    pub fn calibrate<SPI, SpiE, O>(
+        radio: &mut Cc1101<SPI, O>,
+        channels: [u8; N],
+        temperature: Option<i16>,
+        max_drift: u16,
+    ) -> Result<Self, Error<SpiE>>
+    where
+        SPI: SpiDevice<u8, Error = SpiE>,
+        O: FnMut(StatusByte),
+    {
+        let mut table = CalibrationTable {
+            channels,
+            results: [FsCal::default(); N],
+            temperature,
+            max_drift,
+        };
+        table.recalibrate(radio)?;
+        Ok(table)
+    }
+
+    /// Calibrated channels.
+    pub fn channels(&self) -> &[u8; N] {
+        &self.channels
+    }
+
+    /// Stored calibration results for `channel`.
+    pub fn get(&self, channel: u8) -> Option<FsCal> {
+        let index = self.channels.iter().position(|&c| c == channel)?;
+        Some(self.results[index])
+    }
+
+    /// Calibrate all channels again, e.g. after changing the base frequency or channel spacing.
+    pub fn recalibrate<SPI, SpiE, O>(
+        &mut self,
+        radio: &mut Cc1101<SPI, O>,
+    ) -> Result<(), Error<SpiE>>
+    where
+        SPI: SpiDevice<u8, Error = SpiE>,
+        O: FnMut(StatusByte),
+    {
+        radio.exit_rx_tx()?;
+        radio.await_machine_state(MachineState::IDLE)?;
+        radio.set_autocalibration(AutoCalibration::Disabled)?;
+
+        for (channel, result) in self.channels.iter().zip(self.results.iter_mut()) {
+            radio.set_channel(*channel)?;
+            radio.cal_freq_synth_and_turn_off()?;
+            radio.await_machine_state(MachineState::IDLE)?;
+            *result = radio.get_fs_calibration()?;
+        }
+        Ok(())
+    }
+
+    /// Report the chip temperature (in degrees Celsius), e.g. measured with the analog temperature
+    /// sensor. Recalibrates all channels when the temperature drifted by more than the allowed
+    /// amount since calibration, returns whether it did.
+    pub fn update_temperature<SPI, SpiE, O>(
+        &mut self,
+        radio: &mut Cc1101<SPI, O>,
+        temperature: i16,
+    ) -> Result<bool, Error<SpiE>>
+    where
+        SPI: SpiDevice<u8, Error = SpiE>,
+        O: FnMut(StatusByte),
+    {
+        let drifted = match self.temperature {
+            Some(calibrated) => calibrated.abs_diff(temperature) > self.max_drift,
+            None => false,
+        };
+        if self.temperature.is_none() || drifted {
+            self.temperature = Some(temperature);
+        }
+        if drifted {
+            self.recalibrate(radio)?;
+        }
+        Ok(drifted)
+    }
+
+    /// Switch to `channel` by writing CHANNR and the stored calibration results, without
+    /// calibrating. The radio is left in IDLE, ready for SRX or STX.
+    pub fn hop<SPI, SpiE, O>(
+        &self,
+        radio: &mut Cc1101<SPI, O>,
+        channel: u8,
+    ) -> Result<(), Error<SpiE>>
+    where
+        SPI: SpiDevice<u8, Error = SpiE>,
+        O: FnMut(StatusByte),
+    {
+        let result = self
+            .get(channel)
+            .ok_or(Error::UserInputError(UserError::UncalibratedChannel(channel)))?;
+
+        radio.exit_rx_tx()?;
+        radio.await_machine_state(MachineState::IDLE)?;
+        radio.set_channel(channel)?;
+        radio.set_fs_calibration(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lowlevel::registers::{Command, Config};
+    use crate::mock::{radio, Chip};
+
+    fn fs_cal(channel: u8) -> FsCal {
+        FsCal {
+            fscal3: 0xE9,
+            fscal2: 0x2A,
+            fscal1: channel,
+        }
+    }
+
+    #[test]
+    fn test_calibrate() {
+        let mut radio = radio();
+        radio.set_autocalibration(AutoCalibration::FromIdle).unwrap();
+        let table = CalibrationTable::calibrate(&mut radio, [3, 10, 42], None, 10).unwrap();
+
+        assert_eq!(table.get(3), Some(fs_cal(3)));
+        assert_eq!(table.get(10), Some(fs_cal(10)));
+        assert_eq!(table.get(42), Some(fs_cal(42)));
+        assert_eq!(table.get(4), None);
+
+        let chip = &radio.0.spi;
+        assert_eq!(chip.strobes.iter().filter(|&&cmd| cmd == Command::SCAL).count(), 3);
+        assert_eq!(chip.get(Config::CHANNR), 42);
+        // MCSM0.FS_AUTOCAL
+        assert_eq!(chip.get(Config::MCSM0) & 0x30, 0);
+    }
+
+    #[test]
+    fn test_hop() {
+        let mut radio = radio();
+        let table = CalibrationTable::calibrate(&mut radio, [3, 10], None, 10).unwrap();
+        radio.enable_rx().unwrap();
+        let strobes = radio.0.spi.strobes.len();
+
+        table.hop(&mut radio, 3).unwrap();
+        let chip = &radio.0.spi;
+        assert_eq!(chip.state, MachineState::IDLE);
+        assert_eq!(chip.get(Config::CHANNR), 3);
+        assert_eq!(chip.config[Config::FSCAL3 as usize..=Config::FSCAL1 as usize], [0xE9, 0x2A, 3]);
+        assert_eq!(chip.strobes[strobes..], [Command::SIDLE]);
+
+        assert_eq!(
+            table.hop(&mut radio, 4),
+            Err(Error::UserInputError(UserError::UncalibratedChannel(4)))
+        );
+        assert_eq!(radio.0.spi.get(Config::CHANNR), 3);
+    }
+
+    #[test]
+    fn test_update_temperature() {
+        let mut radio = radio();
+        let mut table = CalibrationTable::calibrate(&mut radio, [3, 10], Some(20), 10).unwrap();
+        let calibrations = |radio: &Cc1101<Chip>| {
+            radio.0.spi.strobes.iter().filter(|&&cmd| cmd == Command::SCAL).count()
+        };
+
+        assert_eq!(table.update_temperature(&mut radio, 30), Ok(false));
+        assert_eq!(table.update_temperature(&mut radio, 10), Ok(false));
+        assert_eq!(calibrations(&radio), 2);
+
+        // Drift measured from the temperature at the last calibration
+        assert_eq!(table.update_temperature(&mut radio, 31), Ok(true));
+        assert_eq!(calibrations(&radio), 4);
+        assert_eq!(table.update_temperature(&mut radio, 40), Ok(false));
+    }
+}
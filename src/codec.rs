@@ -0,0 +1,22 @@
+//! Software implementations of the CC1101 packet handling, bit-exact with the chip.
+//!
+//! These construct or verify CC1101 frames where the chip does no packet handling, e.g. in
+//! synchronous or asynchronous serial mode, or from SDR recordings.
+//!
+//! * [`crc16`] computes the CRC-16 appended with PKTCTRL0.CRC_EN, see DN502.
+//! * [`Pn9`] generates the whitening sequence applied with PKTCTRL0.WHITE_DATA, see DN509.
+//! * [`fec_encode`] and [`fec_decode`] implement the convolutional code and interleaving enabled
+//!   with PKTCTRL0.FEC_EN, see DN504.
+//! * [`Framing`] combines CRC and whitening into complete frames, sent and received by
+//!   [`Cc1101::transmit_framed`](crate::Cc1101::transmit_framed) and
+//!   [`Cc1101::receive_framed`](crate::Cc1101::receive_framed).
+
+mod crc;
+mod fec;
+mod framing;
+mod whitening;
+
+pub use self::crc::*;
+pub use self::fec::*;
+pub use self::framing::*;
+pub use self::whitening::*;
@@ -0,0 +1,50 @@
+/// CRC-16 generator polynomial, x^16 + x^15 + x^2 + 1.
+pub const CRC16_POLY: u16 = 0x8005;
+/// CRC-16 initial value.
+pub const CRC16_INIT: u16 = 0xFFFF;
+
+/// CRC-16 of `data` as computed by the CC1101, over the length, address and payload bytes.
+/// The CRC is sent MSB first after the payload.
+pub fn crc16(data: &[u8]) -> u16 {
+    crc16_update(CRC16_INIT, data)
+}
+
+/// Continue a CRC-16 computation from `crc` over `data`.
+pub fn crc16_update(crc: u16, data: &[u8]) -> u16 {
+    data.iter().fold(crc, |mut crc, &byte| {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = match crc & 0x8000 != 0 {
+                true => (crc << 1) ^ CRC16_POLY,
+                false => crc << 1,
+            };
+        }
+        crc
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_value() {
+        assert_eq!(crc16(b"123456789"), 0xAEE7);
+        assert_eq!(crc16(&[]), CRC16_INIT);
+    }
+
+    #[test]
+    fn test_incremental() {
+        let crc = crc16_update(crc16(b"1234"), b"56789");
+        assert_eq!(crc, crc16(b"123456789"));
+    }
+
+    #[test]
+    fn test_reference_frame() {
+        // DN502 example frame: length and payload, followed by the CRC sent MSB first
+        let frame = [0x03, 0x01, 0x02, 0x03];
+        let crc = crc16(&frame);
+        assert_eq!(crc, 0x303A);
+        assert_eq!(crc16_update(crc, &crc.to_be_bytes()), 0);
+    }
+}
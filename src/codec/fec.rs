@@ -0,0 +1,231 @@
+/// Convolutional encoder output symbol, indexed by the previous three input bits and the current one.
+const ENCODE_TABLE: [u8; 16] = [0, 3, 1, 2, 3, 0, 2, 1, 3, 0, 2, 1, 0, 3, 1, 2];
+/// Appended to the data to bring the encoder back to a known state.
+const TERMINATOR: u8 = 0x0B;
+/// Decoded bits kept per state before the oldest byte is output.
+const TRACEBACK_BITS: u32 = 24;
+
+/// FEC encoding or decoding failure.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum FecError {
+    /// Output buffer too small.
+    BufferTooSmall,
+    /// Encoded data is not a whole number of 4 byte interleaver blocks.
+    InvalidLength,
+}
+
+/// Length of the data including the trellis terminator, an even number of bytes.
+fn terminated_len(len: usize) -> usize {
+    2 * (len / 2 + 1)
+}
+
+/// Length of `len` data bytes after FEC encoding and interleaving.
+pub fn fec_encoded_len(len: usize) -> usize {
+    2 * terminated_len(len)
+}
+
+/// FEC encode and interleave `data`, as done by the CC1101 with PKTCTRL0.FEC_EN, see DN504.
+/// `data` is the length byte, address, payload and CRC. Returns the encoded length.
+pub fn fec_encode(data: &[u8], out: &mut [u8]) -> Result<usize, FecError> {
+    let len = fec_encoded_len(data.len());
+    let out = out.get_mut(..len).ok_or(FecError::BufferTooSmall)?;
+
+    let mut state = 0;
+    let input = data.iter().copied().chain([TERMINATOR; 2]);
+    for (byte, pair) in input.zip(out.chunks_exact_mut(2)) {
+        let mut symbols = 0u16;
+        for bit in (0..8).rev() {
+            let index = (state << 1) | (byte >> bit) & 1;
+            symbols = (symbols << 2) | ENCODE_TABLE[index as usize] as u16;
+            state = index & 0x07;
+        }
+        pair.copy_from_slice(&symbols.to_be_bytes());
+    }
+
+    for block in out.chunks_exact_mut(4) {
+        let interleaved = interleave(block.try_into().unwrap());
+        block.copy_from_slice(&interleaved);
+    }
+    Ok(len)
+}
+
+/// De-interleave and FEC decode `encoded` with a hard decision Viterbi decoder. The decoded data
+/// is followed by one or two trellis terminator bytes, the length byte of the frame tells where
+/// the data ends. Returns the decoded length, half the encoded length.
+pub fn fec_decode(encoded: &[u8], out: &mut [u8]) -> Result<usize, FecError> {
+    if !encoded.len().is_multiple_of(4) {
+        return Err(FecError::InvalidLength);
+    }
+    let len = encoded.len() / 2;
+    let out = out.get_mut(..len).ok_or(FecError::BufferTooSmall)?;
+
+    let mut costs = [u16::MAX; 8];
+    costs[0] = 0;
+    // Decoded bits of the surviving path into each state, newest in the LSB
+    let mut paths = [0u32; 8];
+    let mut depth = 0;
+    let mut written = 0;
+
+    for block in encoded.chunks_exact(4) {
+        let block = deinterleave(block.try_into().unwrap());
+        for pair in block.chunks_exact(2) {
+            let symbols = u16::from_be_bytes([pair[0], pair[1]]);
+            for i in (0..8).rev() {
+                let symbol = (symbols >> (2 * i)) as u8 & 0x03;
+                let mut next_costs = [u16::MAX; 8];
+                let mut next_paths = [0u32; 8];
+                for next in 0..8 {
+                    let bit = next as u8 & 1;
+                    for prev in [next >> 1, (next >> 1) | 0x04] {
+                        if costs[prev] == u16::MAX {
+                            continue;
+                        }
+                        let expected = ENCODE_TABLE[(prev << 1) | bit as usize];
+                        let cost = costs[prev] + (expected ^ symbol).count_ones() as u16;
+                        if cost < next_costs[next] {
+                            next_costs[next] = cost;
+                            next_paths[next] = (paths[prev] << 1) | bit as u32;
+                        }
+                    }
+                }
+                // Keep the path costs bounded for any input length
+                let min = next_costs.iter().copied().min().unwrap_or(0);
+                for cost in next_costs.iter_mut().filter(|cost| **cost != u16::MAX) {
+                    *cost -= min;
+                }
+                costs = next_costs;
+                paths = next_paths;
+                depth += 1;
+
+                if depth == TRACEBACK_BITS + 8 {
+                    out[written] = (paths[best(&costs)] >> TRACEBACK_BITS) as u8;
+                    written += 1;
+                    depth -= 8;
+                }
+            }
+        }
+    }
+
+    let path = paths[best(&costs)];
+    while depth > 0 {
+        depth -= 8;
+        out[written] = (path >> depth) as u8;
+        written += 1;
+    }
+    Ok(len)
+}
+
+fn best(costs: &[u16; 8]) -> usize {
+    (0..8).min_by_key(|&state| costs[state]).unwrap_or(0)
+}
+
+/// Interleave a block of 16 symbols, written row by row and read column by column.
+fn interleave(block: [u8; 4]) -> [u8; 4] {
+    let mut output = 0u32;
+    for j in 0..16 {
+        let symbol = (block[3 - (j & 0x03)] >> (2 * (j >> 2))) & 0x03;
+        output = (output << 2) | symbol as u32;
+    }
+    output.to_be_bytes()
+}
+
+/// Inverse of [`interleave`].
+fn deinterleave(block: [u8; 4]) -> [u8; 4] {
+    let input = u32::from_be_bytes(block);
+    let mut output = [0; 4];
+    for j in 0..16 {
+        let symbol = (input >> (30 - 2 * j)) as u8 & 0x03;
+        output[3 - (j & 0x03)] |= symbol << (2 * (j >> 2));
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DATA: [u8; 9] = [0x08, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x42, 0xA5];
+
+    #[test]
+    fn test_impulse_response() {
+        // A single 1 bit followed by zeros, before interleaving
+        let mut out = [0; 8];
+        assert_eq!(fec_encode(&[0x80, 0x00], &mut out), Ok(8));
+        let mut block = [0; 4];
+        block.copy_from_slice(&out[..4]);
+        assert_eq!(deinterleave(block), [0b1101_1111, 0x00, 0x00, 0x00]);
+    }
+
+    #[test]
+    fn test_dn504_example() {
+        // Length 3, payload 01 02 03 and CRC 303A, encoded as by the DN504 reference code
+        let frame = [0x03, 0x01, 0x02, 0x03, 0x30, 0x3A];
+        let mut out = [0; 16];
+        assert_eq!(fec_encode(&frame, &mut out), Ok(16));
+        assert_eq!(
+            out,
+            [
+                0xC8, 0x3C, 0x00, 0x20, 0x84, 0xCF, 0x33, 0x31, 0xA2, 0xFC, 0x40, 0x4A, 0x44, 0x30,
+                0x47, 0xEF
+            ]
+        );
+
+        let mut decoded = [0; 8];
+        assert_eq!(fec_decode(&out, &mut decoded), Ok(8));
+        assert_eq!(decoded, [0x03, 0x01, 0x02, 0x03, 0x30, 0x3A, TERMINATOR, TERMINATOR]);
+    }
+
+    #[test]
+    fn test_long_input() {
+        // Path costs must not saturate over long inputs with errors
+        let data = [0x5A; 250];
+        let mut encoded = [0; 504];
+        let len = fec_encode(&data, &mut encoded).unwrap();
+        for byte in encoded[..len].iter_mut().step_by(8) {
+            *byte ^= 0x01;
+        }
+        let mut decoded = [0; 252];
+        assert_eq!(fec_decode(&encoded[..len], &mut decoded), Ok(252));
+        assert_eq!(decoded[..250], data);
+    }
+
+    #[test]
+    fn test_interleave() {
+        let block = [0x12, 0x34, 0x56, 0x78];
+        assert_ne!(interleave(block), block);
+        assert_eq!(deinterleave(interleave(block)), block);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut encoded = [0; 24];
+        let len = fec_encode(&DATA, &mut encoded).unwrap();
+        assert_eq!(len, fec_encoded_len(DATA.len()));
+        assert_eq!(len, 20);
+
+        let mut decoded = [0; 12];
+        assert_eq!(fec_decode(&encoded[..len], &mut decoded), Ok(10));
+        assert_eq!(decoded[..9], DATA);
+        assert_eq!(decoded[9], TERMINATOR);
+    }
+
+    #[test]
+    fn test_corrects_bit_errors() {
+        let mut encoded = [0; 20];
+        let len = fec_encode(&DATA, &mut encoded).unwrap();
+        encoded[1] ^= 0x08;
+        encoded[9] ^= 0x40;
+        encoded[17] ^= 0x01;
+
+        let mut decoded = [0; 10];
+        assert_eq!(fec_decode(&encoded[..len], &mut decoded), Ok(10));
+        assert_eq!(decoded[..9], DATA);
+    }
+
+    #[test]
+    fn test_invalid_length() {
+        assert_eq!(fec_decode(&[0; 6], &mut [0; 8]), Err(FecError::InvalidLength));
+        assert_eq!(fec_encode(&DATA, &mut [0; 19]), Err(FecError::BufferTooSmall));
+    }
+}
@@ -0,0 +1,86 @@
+use super::{crc16, whiten};
+
+/// Software packet handling, matching PKTCTRL0.CRC_EN and PKTCTRL0.WHITE_DATA.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Framing {
+    /// Append and check a CRC-16.
+    pub crc: bool,
+    /// Whiten the frame, including the CRC.
+    pub whitening: bool,
+}
+
+/// Frame encoding or decoding failure.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum FrameError {
+    /// Buffer cannot hold the CRC, or is shorter than one.
+    BufferTooSmall,
+    /// Received CRC does not match the frame contents.
+    CrcMismatch,
+}
+
+impl Framing {
+    /// Bytes added to the frame.
+    pub fn overhead(&self) -> usize {
+        if self.crc {
+            2
+        } else {
+            0
+        }
+    }
+
+    /// Encode the frame in `buf[..len]`, the length and address fields (if any) followed by the
+    /// payload, in place. Returns the length of the encoded frame.
+    pub fn encode(&self, buf: &mut [u8], len: usize) -> Result<usize, FrameError> {
+        let total = len + self.overhead();
+        if total > buf.len() {
+            return Err(FrameError::BufferTooSmall);
+        }
+        if self.crc {
+            let crc = crc16(&buf[..len]);
+            buf[len..total].copy_from_slice(&crc.to_be_bytes());
+        }
+        if self.whitening {
+            whiten(&mut buf[..total]);
+        }
+        Ok(total)
+    }
+
+    /// Decode the received frame in `frame` in place. Returns the length of the frame without the
+    /// CRC.
+    pub fn decode(&self, frame: &mut [u8]) -> Result<usize, FrameError> {
+        let len = frame.len().checked_sub(self.overhead()).ok_or(FrameError::BufferTooSmall)?;
+        if self.whitening {
+            whiten(frame);
+        }
+        if self.crc && crc16(frame) != 0 {
+            return Err(FrameError::CrcMismatch);
+        }
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let framing = Framing {
+            crc: true,
+            whitening: true,
+        };
+        let mut buf = [0; 8];
+        buf[..4].copy_from_slice(&[0x03, 0x01, 0x02, 0x03]);
+        let len = framing.encode(&mut buf, 4).unwrap();
+        assert_eq!(len, 6);
+
+        let mut received = buf;
+        assert_eq!(framing.decode(&mut received[..len]), Ok(4));
+        assert_eq!(received[..4], [0x03, 0x01, 0x02, 0x03]);
+
+        buf[2] ^= 0x10;
+        assert_eq!(framing.decode(&mut buf[..len]), Err(FrameError::CrcMismatch));
+    }
+}
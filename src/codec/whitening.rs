@@ -0,0 +1,68 @@
+/// PN9 sequence generator used for data whitening, x^9 + x^5 + 1 with all ones as seed.
+///
+/// Whitening XORs each byte, from the length byte up to and including the CRC, with the next byte
+/// of the sequence. The CRC is computed over the data before whitening. Applying the same
+/// sequence again removes the whitening.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Pn9 {
+    state: u16,
+}
+
+impl Pn9 {
+    pub fn new() -> Self {
+        Pn9 {
+            state: 0x1FF,
+        }
+    }
+
+    /// Next byte of the sequence.
+    pub fn next_byte(&mut self) -> u8 {
+        let byte = self.state as u8;
+        for _ in 0..8 {
+            let feedback = (self.state ^ (self.state >> 5)) & 1;
+            self.state = (self.state >> 1) | (feedback << 8);
+        }
+        byte
+    }
+
+    /// XOR `data` with the next bytes of the sequence.
+    pub fn apply(&mut self, data: &mut [u8]) {
+        for byte in data {
+            *byte ^= self.next_byte();
+        }
+    }
+}
+
+impl Default for Pn9 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Whiten or de-whiten `data`, which starts at the length byte of a frame.
+pub fn whiten(data: &mut [u8]) {
+    Pn9::new().apply(data);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sequence() {
+        // DN509, first bytes of the PN9 sequence
+        let mut sequence = [0; 8];
+        whiten(&mut sequence);
+        assert_eq!(sequence, [0xFF, 0xE1, 0x1D, 0x9A, 0xED, 0x85, 0x33, 0x24]);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut data = *b"whitened";
+        whiten(&mut data);
+        assert_ne!(&data, b"whitened");
+        whiten(&mut data);
+        assert_eq!(&data, b"whitened");
+    }
+}
@@ -0,0 +1,246 @@
+//! Radio events derived from the GDO pins.
+//!
+//! [`EventDriven`] owns the radio together with the input pins connected to GDO0 and, optionally,
+//! GDO2. The signals routed to the pins with [`EventDriven::set_gdo0_config`] and
+//! [`EventDriven::set_gdo2_config`] are sampled by [`EventDriven::poll`], and their edges are
+//! translated into [`Event`]s, e.g. from an interrupt handler or a low priority loop, instead of
+//! polling RXBYTES over SPI.
+//!
+//! The pins are sampled, not latched: a pulse shorter than the interval between two polls, e.g.
+//! SYNC_WORD for a short packet, or CRC_OK which is only asserted until the RX FIFO is read, is
+//! missed. Call [`EventDriven::poll`] from the pin interrupt to catch every edge.
+
+use crate::{Cc1101, Error, GdoCfg, StatusByte};
+use core::convert::Infallible;
+use hal::digital::{self, ErrorType, InputPin, PinState};
+use hal::spi::SpiDevice;
+
+/// Event signalled on a GDO pin.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Event {
+    /// A packet has been received (end of packet, or CRC OK).
+    PacketReceived,
+    /// A packet has been sent.
+    PacketSent,
+    /// Sync word sent or received.
+    SyncDetected,
+    /// Preamble quality threshold reached.
+    PreambleDetected,
+    /// RX FIFO filled above, or TX FIFO drained below, the FIFO threshold.
+    FifoThreshold,
+    /// Channel busy, RSSI above threshold.
+    ChannelBusy,
+    /// Channel clear, RSSI below threshold.
+    ChannelClear,
+    /// Frequency synthesizer PLL in lock.
+    PllLocked,
+    /// RX FIFO overflowed.
+    RxFifoOverflow,
+    /// TX FIFO underflowed.
+    TxFifoUnderflow,
+}
+
+/// Placeholder for an unconnected GDO pin, always low.
+pub struct NoPin;
+
+impl ErrorType for NoPin {
+    type Error = Infallible;
+}
+
+impl InputPin for NoPin {
+    fn is_high(&mut self) -> Result<bool, Self::Error> {
+        Ok(false)
+    }
+
+    fn is_low(&mut self) -> Result<bool, Self::Error> {
+        Ok(true)
+    }
+}
+
+struct Gdo<P> {
+    pin: P,
+    config: Option<GdoCfg>,
+    level: bool,
+}
+
+impl<P: InputPin> Gdo<P> {
+    fn new(pin: P) -> Self {
+        Gdo {
+            pin,
+            config: None,
+            level: false,
+        }
+    }
+
+    fn sample<SpiE>(&mut self) -> Result<bool, Error<SpiE>> {
+        self.pin.is_high().map_err(|e| Error::Gdo(digital::Error::kind(&e)))
+    }
+
+    /// Sample the pin, and translate a change of level to an event.
+    fn update<SpiE>(&mut self, transmitting: bool) -> Result<Option<Event>, Error<SpiE>> {
+        let level = self.sample()?;
+        if level == self.level {
+            return Ok(None);
+        }
+        self.level = level;
+        Ok(self.event(level, transmitting))
+    }
+
+    /// Translate a change of the pin level to an event, given the configured signal.
+    fn event(&self, rising: bool, transmitting: bool) -> Option<Event> {
+        match (self.config?, rising) {
+            (GdoCfg::SYNC_WORD, true) => Some(Event::SyncDetected),
+            (GdoCfg::SYNC_WORD, false) if transmitting => Some(Event::PacketSent),
+            (GdoCfg::SYNC_WORD, false) => Some(Event::PacketReceived),
+            (GdoCfg::CRC_OK, true) => Some(Event::PacketReceived),
+            (GdoCfg::PQT_REACHED, true) => Some(Event::PreambleDetected),
+            (GdoCfg::RX_FIFO_FILLED | GdoCfg::RX_FIFO_FILLED_END_OF_PKT, true) => {
+                Some(Event::FifoThreshold)
+            }
+            (GdoCfg::TX_FIFO_FILLED, false) => Some(Event::FifoThreshold),
+            (GdoCfg::CHANNEL_CLEAR, false) | (GdoCfg::CARRIER_SENSE, true) => {
+                Some(Event::ChannelBusy)
+            }
+            (GdoCfg::CHANNEL_CLEAR, true) | (GdoCfg::CARRIER_SENSE, false) => {
+                Some(Event::ChannelClear)
+            }
+            (GdoCfg::PLL_LOCK, true) => Some(Event::PllLocked),
+            (GdoCfg::RX_FIFO_OVERFLOW, true) => Some(Event::RxFifoOverflow),
+            (GdoCfg::TX_FIFO_UNDERFLOW, true) => Some(Event::TxFifoUnderflow),
+            _ => None,
+        }
+    }
+}
+
+/// Radio driver reporting events from the GDO0 and GDO2 pins.
+pub struct EventDriven<SPI, GDO0, GDO2 = NoPin, O = fn(StatusByte)> {
+    radio: Cc1101<SPI, O>,
+    gdo0: Gdo<GDO0>,
+    gdo2: Gdo<GDO2>,
+    transmitting: bool,
+}
+
+impl<SPI, SpiE, GDO0, GDO2, O> EventDriven<SPI, GDO0, GDO2, O>
+where
+    SPI: SpiDevice<u8, Error = SpiE>,
+    GDO0: InputPin,
+    GDO2: InputPin,
+    O: FnMut(StatusByte),
+{
+    /// Take ownership of the radio and the pins connected to GDO0 and GDO2, use [`NoPin`] for an
+    /// unconnected GDO2.
+    pub fn new(radio: Cc1101<SPI, O>, gdo0: GDO0, gdo2: GDO2) -> Self {
+        EventDriven {
+            radio,
+            gdo0: Gdo::new(gdo0),
+            gdo2: Gdo::new(gdo2),
+            transmitting: false,
+        }
+    }
+
+    /// Release the radio and the pins.
+    pub fn release(self) -> (Cc1101<SPI, O>, GDO0, GDO2) {
+        (self.radio, self.gdo0.pin, self.gdo2.pin)
+    }
+
+    /// Access the radio, e.g. to read received data.
+    pub fn radio(&mut self) -> &mut Cc1101<SPI, O> {
+        &mut self.radio
+    }
+
+    /// Route a signal to GDO0, active high.
+    pub fn set_gdo0_config(&mut self, config: GdoCfg) -> Result<(), Error<SpiE>> {
+        self.radio.set_gdo0_config(config)?;
+        self.radio.set_gdo0_active_state(PinState::High)?;
+        self.gdo0.config = Some(config);
+        self.gdo0.level = self.gdo0.sample()?;
+        Ok(())
+    }
+
+    /// Route a signal to GDO2, active high.
+    pub fn set_gdo2_config(&mut self, config: GdoCfg) -> Result<(), Error<SpiE>> {
+        self.radio.set_gdo2_config(config)?;
+        self.radio.set_gdo2_active_state(PinState::High)?;
+        self.gdo2.config = Some(config);
+        self.gdo2.level = self.gdo2.sample()?;
+        Ok(())
+    }
+
+    /// Command Strobe: Enable RX, end of packet on SYNC_WORD is reported as received.
+    pub fn enable_rx(&mut self) -> Result<(), Error<SpiE>> {
+        self.transmitting = false;
+        self.radio.enable_rx()
+    }
+
+    /// Command Strobe: Enable TX, end of packet on SYNC_WORD is reported as sent.
+    pub fn enable_tx(&mut self) -> Result<(), Error<SpiE>> {
+        self.transmitting = true;
+        self.radio.enable_tx()
+    }
+
+    /// Sample the GDO pins and return the event for the first pin that changed level.
+    /// A change on GDO2 is reported by the next call when GDO0 also produced an event.
+    /// Only levels are compared, so a pin going high and low again between two calls produces
+    /// no event.
+    pub fn poll(&mut self) -> Result<Option<Event>, Error<SpiE>> {
+        if let Some(event) = self.gdo0.update(self.transmitting)? {
+            return Ok(Some(event));
+        }
+        self.gdo2.update(self.transmitting)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gdo(config: GdoCfg) -> Gdo<NoPin> {
+        let mut gdo = Gdo::new(NoPin);
+        gdo.config = Some(config);
+        gdo
+    }
+
+    #[test]
+    fn test_sync_word() {
+        let gdo = gdo(GdoCfg::SYNC_WORD);
+        assert_eq!(gdo.event(true, false), Some(Event::SyncDetected));
+        assert_eq!(gdo.event(true, true), Some(Event::SyncDetected));
+        assert_eq!(gdo.event(false, false), Some(Event::PacketReceived));
+        assert_eq!(gdo.event(false, true), Some(Event::PacketSent));
+    }
+
+    #[test]
+    fn test_edges() {
+        let cases = [
+            (GdoCfg::CRC_OK, true, Some(Event::PacketReceived)),
+            (GdoCfg::CRC_OK, false, None),
+            (GdoCfg::PQT_REACHED, true, Some(Event::PreambleDetected)),
+            (GdoCfg::PQT_REACHED, false, None),
+            (GdoCfg::RX_FIFO_FILLED, true, Some(Event::FifoThreshold)),
+            (GdoCfg::RX_FIFO_FILLED_END_OF_PKT, true, Some(Event::FifoThreshold)),
+            (GdoCfg::RX_FIFO_FILLED, false, None),
+            (GdoCfg::TX_FIFO_FILLED, false, Some(Event::FifoThreshold)),
+            (GdoCfg::TX_FIFO_FILLED, true, None),
+            (GdoCfg::CHANNEL_CLEAR, false, Some(Event::ChannelBusy)),
+            (GdoCfg::CHANNEL_CLEAR, true, Some(Event::ChannelClear)),
+            (GdoCfg::CARRIER_SENSE, true, Some(Event::ChannelBusy)),
+            (GdoCfg::CARRIER_SENSE, false, Some(Event::ChannelClear)),
+            (GdoCfg::PLL_LOCK, true, Some(Event::PllLocked)),
+            (GdoCfg::PLL_LOCK, false, None),
+            (GdoCfg::RX_FIFO_OVERFLOW, true, Some(Event::RxFifoOverflow)),
+            (GdoCfg::TX_FIFO_UNDERFLOW, true, Some(Event::TxFifoUnderflow)),
+            (GdoCfg::CHIP_RDYn, true, None),
+        ];
+        for (config, rising, event) in cases {
+            assert_eq!(gdo(config).event(rising, false), event, "{:?} {}", config, rising);
+        }
+    }
+
+    #[test]
+    fn test_unconfigured() {
+        let gdo = Gdo::new(NoPin);
+        assert_eq!(gdo.event(true, false), None);
+        assert_eq!(gdo.event(false, true), None);
+    }
+}
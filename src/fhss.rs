@@ -0,0 +1,533 @@
+//! Frequency-hopping spread spectrum scheduler.
+//!
+//! A [`Hopper`] divides time, read from a [`TimeSource`], into slots of a fixed dwell time, and
+//! retunes the radio to the next channel of a [`HopSequence`] at each slot boundary. The
+//! transmitter starts the schedule and periodically sends a [`Beacon`] with its position in the
+//! sequence. The receiver synchronizes its own schedule from a received beacon.
+//!
+//! The sequence and dwell time can be checked against a [`DwellLimit`], such as the FCC 15.247
+//! limits for the 902-928 MHz band.
+
+use crate::calibration::CalibrationTable;
+use crate::{AutoCalibration, Cc1101, Error, MachineState, StatusByte};
+use hal::spi::SpiDevice;
+
+/// Size of an encoded [`Beacon`].
+pub const BEACON_SIZE: usize = 5;
+
+/// Monotonic millisecond clock, wrapping around at `u32::MAX`.
+pub trait TimeSource {
+    fn now_ms(&mut self) -> u32;
+}
+
+/// Order in which channels are visited.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct HopSequence<const N: usize> {
+    channels: [u8; N],
+}
+
+impl<const N: usize> HopSequence<N> {
+    /// Visit the channels in the given order.
+    pub fn explicit(channels: [u8; N]) -> Self {
+        HopSequence {
+            channels,
+        }
+    }
+
+    /// Visit the channels `first_channel..first_channel + N` in a pseudo-random order derived from
+    /// `seed`. Transmitter and receiver using the same seed get the same sequence.
+    pub fn pseudo_random(seed: u32, first_channel: u8) -> Self {
+        let mut channels = [0; N];
+        for (i, channel) in channels.iter_mut().enumerate() {
+            *channel = first_channel.wrapping_add(i as u8);
+        }
+
+        // Fisher-Yates shuffle driven by a xorshift generator, which must not start at 0
+        let mut state = if seed == 0 {
+            0x9E37_79B9
+        } else {
+            seed
+        };
+        for i in (1..N).rev() {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            channels.swap(i, state as usize % (i + 1));
+        }
+
+        HopSequence {
+            channels,
+        }
+    }
+
+    pub fn channels(&self) -> &[u8; N] {
+        &self.channels
+    }
+
+    /// Channel at position `index` of the sequence.
+    pub fn channel(&self, index: usize) -> u8 {
+        self.channels[index % N]
+    }
+}
+
+/// Regulatory limits on the time spent on a single channel.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct DwellLimit {
+    /// Maximum occupancy (in milliseconds) of any channel within `period_ms`.
+    pub max_dwell_ms: u32,
+    /// Observation period (in milliseconds).
+    pub period_ms: u32,
+    /// Minimum number of distinct hopping channels.
+    pub min_channels: usize,
+}
+
+impl DwellLimit {
+    /// FCC 15.247(a)(1)(i), 902-928 MHz band, 20 dB bandwidth below 250 kHz.
+    pub const FCC_15_247_NARROW: DwellLimit = DwellLimit {
+        max_dwell_ms: 400,
+        period_ms: 20_000,
+        min_channels: 50,
+    };
+
+    /// FCC 15.247(a)(1)(i), 902-928 MHz band, 20 dB bandwidth of 250 kHz or more.
+    pub const FCC_15_247_WIDE: DwellLimit = DwellLimit {
+        max_dwell_ms: 400,
+        period_ms: 10_000,
+        min_channels: 25,
+    };
+
+    /// Check a hop sequence visiting each channel for `dwell_ms` against the limit.
+    pub fn check(&self, channels: &[u8], dwell_ms: u32) -> Result<(), HopConfigError> {
+        if channels.is_empty() || channels.len() > 256 || dwell_ms == 0 {
+            return Err(HopConfigError::Empty);
+        }
+
+        let mut distinct = 0;
+        for (i, channel) in channels.iter().enumerate() {
+            if !channels[..i].contains(channel) {
+                distinct += 1;
+            }
+        }
+        if distinct < self.min_channels {
+            return Err(HopConfigError::TooFewChannels(distinct));
+        }
+
+        let cycle_ms = channels.len() as u64 * dwell_ms as u64;
+        let period_ms = self.period_ms as u64;
+        for channel in channels {
+            let visits = channels.iter().filter(|&c| c == channel).count() as u64;
+            let per_cycle_ms = visits * dwell_ms as u64;
+            // Worst case alignment of the observation period with the visits to the channel
+            let occupancy_ms =
+                (period_ms / cycle_ms) * per_cycle_ms + per_cycle_ms.min(period_ms % cycle_ms);
+            if occupancy_ms > self.max_dwell_ms as u64 {
+                return Err(HopConfigError::OccupancyTooLong {
+                    channel: *channel,
+                    occupancy_ms: occupancy_ms.min(u32::MAX as u64) as u32,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Invalid hopping configuration.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum HopConfigError {
+    /// Dwell time of zero, or a hop sequence that is empty or longer than 256 hops.
+    Empty,
+    /// Fewer distinct channels than required by the dwell limit.
+    TooFewChannels(usize),
+    /// Channel occupied for longer than allowed within the observation period.
+    OccupancyTooLong {
+        channel: u8,
+        occupancy_ms: u32,
+    },
+}
+
+/// How the radio is retuned to a channel.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Tuning {
+    /// Write the channel number to CHANNR.
+    Channel,
+    /// Write `base_hz + channel * spacing_hz` to FREQ2..FREQ0.
+    Frequency {
+        base_hz: u64,
+        spacing_hz: u64,
+    },
+}
+
+/// Position in the hop sequence, sent by the transmitter to synchronize receivers.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Beacon {
+    /// Position in the hop sequence.
+    pub hop_index: u8,
+    /// Time (in milliseconds) elapsed since the start of the slot.
+    pub offset_ms: u32,
+}
+
+impl Beacon {
+    pub fn to_bytes(&self) -> [u8; BEACON_SIZE] {
+        let offset = self.offset_ms.to_be_bytes();
+        [self.hop_index, offset[0], offset[1], offset[2], offset[3]]
+    }
+
+    pub fn from_bytes(bytes: &[u8; BEACON_SIZE]) -> Self {
+        Beacon {
+            hop_index: bytes[0],
+            offset_ms: u32::from_be_bytes([bytes[1], bytes[2], bytes[3], bytes[4]]),
+        }
+    }
+}
+
+/// Frequency hopping scheduler for a sequence of `N` hops, at most 256.
+pub struct Hopper<T, const N: usize> {
+    time: T,
+    sequence: HopSequence<N>,
+    dwell_ms: u32,
+    tuning: Tuning,
+    calibration: Option<CalibrationTable<N>>,
+    autocal: AutoCalibration,
+    epoch_ms: Option<u32>,
+    tuned: Option<usize>,
+}
+
+impl<T: TimeSource, const N: usize> Hopper<T, N> {
+    /// Create a scheduler dwelling `dwell_ms` on each channel of `sequence`, checked against
+    /// `limit` if given. The schedule does not run until started or synchronized.
+    pub fn new(
+        time: T,
+        sequence: HopSequence<N>,
+        dwell_ms: u32,
+        limit: Option<DwellLimit>,
+    ) -> Result<Self, HopConfigError> {
+        if N == 0 || N > 256 || dwell_ms == 0 {
+            return Err(HopConfigError::Empty);
+        }
+        if let Some(limit) = limit {
+            limit.check(sequence.channels(), dwell_ms)?;
+        }
+
+        Ok(Hopper {
+            time,
+            sequence,
+            dwell_ms,
+            tuning: Tuning::Channel,
+            calibration: None,
+            autocal: AutoCalibration::Disabled,
+            epoch_ms: None,
+            tuned: None,
+        })
+    }
+
+    /// Select how the radio is retuned, CHANNR by default. Drops the calibration table of
+    /// [`Hopper::calibrate`], and restores the automatic calibration it disabled.
+    pub fn set_tuning<SPI, SpiE, O>(
+        &mut self,
+        radio: &mut Cc1101<SPI, O>,
+        tuning: Tuning,
+    ) -> Result<(), Error<SpiE>>
+    where
+        SPI: SpiDevice<u8, Error = SpiE>,
+        O: FnMut(StatusByte),
+    {
+        if self.calibration.is_some() {
+            radio.set_autocalibration(self.autocal)?;
+            self.calibration = None;
+        }
+        self.tuning = tuning;
+        Ok(())
+    }
+
+    /// Calibrate every channel of the sequence once, and retune by writing CHANNR and the stored
+    /// calibration results, see [`CalibrationTable`]. Automatic calibration is disabled until
+    /// [`Hopper::set_tuning`].
+    pub fn calibrate<SPI, SpiE, O>(
+        &mut self,
+        radio: &mut Cc1101<SPI, O>,
+        temperature: Option<i16>,
+        max_drift: u16,
+    ) -> Result<(), Error<SpiE>>
+    where
+        SPI: SpiDevice<u8, Error = SpiE>,
+        O: FnMut(StatusByte),
+    {
+        if self.calibration.is_none() {
+            self.autocal = radio.get_autocalibration()?;
+        }
+        self.tuning = Tuning::Channel;
+        self.calibration = Some(CalibrationTable::calibrate(
+            radio,
+            *self.sequence.channels(),
+            temperature,
+            max_drift,
+        )?);
+        self.tuned = None;
+        Ok(())
+    }
+
+    /// Calibration table in use, e.g. to report temperature changes.
+    pub fn calibration(&mut self) -> Option<&mut CalibrationTable<N>> {
+        self.calibration.as_mut()
+    }
+
+    /// Start the schedule at the first hop, as transmitter.
+    pub fn start(&mut self) {
+        self.epoch_ms = Some(self.time.now_ms());
+        self.tuned = None;
+    }
+
+    /// Stop the schedule, e.g. after losing the transmitter.
+    pub fn stop(&mut self) {
+        self.epoch_ms = None;
+        self.tuned = None;
+    }
+
+    pub fn is_synchronized(&self) -> bool {
+        self.epoch_ms.is_some()
+    }
+
+    /// Current position in the sequence, and the time elapsed in the slot.
+    pub fn position(&mut self) -> Option<(usize, u32)> {
+        let epoch_ms = self.epoch_ms?;
+        let now_ms = self.time.now_ms();
+        let cycle_ms = N as u64 * self.dwell_ms as u64;
+
+        // Keep the epoch within one cycle, so that the schedule survives the clock wrapping around
+        let mut elapsed_ms = now_ms.wrapping_sub(epoch_ms) as u64;
+        if elapsed_ms >= cycle_ms {
+            let skipped = elapsed_ms - elapsed_ms % cycle_ms;
+            self.epoch_ms = Some(epoch_ms.wrapping_add(skipped as u32));
+            elapsed_ms -= skipped;
+        }
+
+        let index = (elapsed_ms / self.dwell_ms as u64) as usize;
+        let offset_ms = (elapsed_ms % self.dwell_ms as u64) as u32;
+        Some((index, offset_ms))
+    }
+
+    /// Time (in milliseconds) until the next hop.
+    pub fn time_to_next_hop_ms(&mut self) -> Option<u32> {
+        let (_, offset_ms) = self.position()?;
+        Some(self.dwell_ms - offset_ms)
+    }
+
+    /// Beacon describing the current position, to be sent by the transmitter.
+    pub fn beacon(&mut self) -> Option<Beacon> {
+        let (index, offset_ms) = self.position()?;
+        Some(Beacon {
+            hop_index: index as u8,
+            offset_ms,
+        })
+    }
+
+    /// Align the schedule to a received beacon. `latency_ms` accounts for the time between the
+    /// beacon being sampled by the transmitter and being processed, e.g. the packet air time.
+    pub fn synchronize(&mut self, beacon: &Beacon, latency_ms: u32) {
+        let elapsed_ms = (beacon.hop_index as u64 % N as u64) * self.dwell_ms as u64
+            + beacon.offset_ms as u64
+            + latency_ms as u64;
+        self.epoch_ms = Some(self.time.now_ms().wrapping_sub(elapsed_ms as u32));
+    }
+
+    /// Retune the radio when a slot boundary has been crossed, returning the new channel.
+    /// The radio is left in IDLE after retuning, ready for SRX or STX.
+    pub fn poll<SPI, SpiE, O>(
+        &mut self,
+        radio: &mut Cc1101<SPI, O>,
+    ) -> Result<Option<u8>, Error<SpiE>>
+    where
+        SPI: SpiDevice<u8, Error = SpiE>,
+        O: FnMut(StatusByte),
+    {
+        let Some((index, _)) = self.position() else {
+            return Ok(None);
+        };
+        if self.tuned == Some(index) {
+            return Ok(None);
+        }
+
+        let channel = self.sequence.channel(index);
+        self.retune(radio, channel)?;
+        self.tuned = Some(index);
+        Ok(Some(channel))
+    }
+
+    fn retune<SPI, SpiE, O>(
+        &mut self,
+        radio: &mut Cc1101<SPI, O>,
+        channel: u8,
+    ) -> Result<(), Error<SpiE>>
+    where
+        SPI: SpiDevice<u8, Error = SpiE>,
+        O: FnMut(StatusByte),
+    {
+        if let Some(table) = &self.calibration {
+            return table.hop(radio, channel);
+        }
+
+        radio.exit_rx_tx()?;
+        radio.await_machine_state(MachineState::IDLE)?;
+        match self.tuning {
+            Tuning::Channel => radio.set_channel(channel),
+            Tuning::Frequency {
+                base_hz,
+                spacing_hz,
+            } => radio.set_frequency(base_hz + channel as u64 * spacing_hz),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lowlevel::registers::Config;
+    use crate::mock::radio;
+
+    struct Clock(u32);
+
+    impl TimeSource for &core::cell::Cell<u32> {
+        fn now_ms(&mut self) -> u32 {
+            self.get()
+        }
+    }
+
+    impl TimeSource for Clock {
+        fn now_ms(&mut self) -> u32 {
+            self.0
+        }
+    }
+
+    #[test]
+    fn test_pseudo_random_sequence_is_permutation() {
+        let sequence = HopSequence::<50>::pseudo_random(0x1234, 10);
+        let mut seen = [false; 50];
+        for channel in sequence.channels() {
+            assert!((10..60).contains(channel));
+            assert!(!seen[(*channel - 10) as usize]);
+            seen[(*channel - 10) as usize] = true;
+        }
+        assert_eq!(sequence, HopSequence::<50>::pseudo_random(0x1234, 10));
+        assert_ne!(sequence, HopSequence::<50>::pseudo_random(0x4321, 10));
+    }
+
+    #[test]
+    fn test_dwell_limit() {
+        let sequence = HopSequence::<50>::pseudo_random(1, 0);
+        let limit = DwellLimit::FCC_15_247_NARROW;
+        assert_eq!(limit.check(sequence.channels(), 400), Ok(()));
+        assert_eq!(
+            limit.check(sequence.channels(), 401),
+            Err(HopConfigError::OccupancyTooLong {
+                channel: sequence.channel(0),
+                occupancy_ms: 401,
+            })
+        );
+        assert_eq!(
+            limit.check(&sequence.channels()[..49], 100),
+            Err(HopConfigError::TooFewChannels(49))
+        );
+
+        // 25 channels of 300 ms, a 10 s period covers one visit and part of the next
+        let sequence = HopSequence::<25>::pseudo_random(1, 0);
+        let limit = DwellLimit::FCC_15_247_WIDE;
+        assert_eq!(
+            limit.check(sequence.channels(), 300),
+            Err(HopConfigError::OccupancyTooLong {
+                channel: sequence.channel(0),
+                occupancy_ms: 600,
+            })
+        );
+        assert_eq!(limit.check(sequence.channels(), 200), Ok(()));
+    }
+
+    #[test]
+    fn test_dwell_limit_rejects_empty() {
+        let limit = DwellLimit {
+            max_dwell_ms: 400,
+            period_ms: 20_000,
+            min_channels: 0,
+        };
+        assert_eq!(limit.check(&[1, 2, 3], 0), Err(HopConfigError::Empty));
+        assert_eq!(limit.check(&[], 100), Err(HopConfigError::Empty));
+        assert_eq!(limit.check(&[1; 257], 100), Err(HopConfigError::Empty));
+        let channels: [u8; 256] = core::array::from_fn(|i| i as u8);
+        assert_eq!(limit.check(&channels, 1), Ok(()));
+    }
+
+    #[test]
+    fn test_position_wraps_around() {
+        let sequence = HopSequence::explicit([3, 1, 2]);
+        let mut hopper = Hopper::new(Clock(u32::MAX - 50), sequence, 100, None).unwrap();
+        assert_eq!(hopper.position(), None);
+
+        hopper.start();
+        assert_eq!(hopper.position(), Some((0, 0)));
+        hopper.time.0 = 49;
+        assert_eq!(hopper.position(), Some((1, 0)));
+        hopper.time.0 = 49 + 250;
+        assert_eq!(hopper.position(), Some((0, 50)));
+        assert_eq!(hopper.time_to_next_hop_ms(), Some(50));
+    }
+
+    #[test]
+    fn test_beacon_synchronizes_receiver() {
+        let clock = core::cell::Cell::new(1000);
+        let sequence = HopSequence::<10>::pseudo_random(7, 0);
+        let mut tx = Hopper::new(&clock, sequence, 50, None).unwrap();
+        let mut rx = Hopper::new(&clock, sequence, 50, None).unwrap();
+        tx.start();
+
+        clock.set(1000 + 50 * 12 + 20);
+        let beacon = Beacon::from_bytes(&tx.beacon().unwrap().to_bytes());
+        assert_eq!(
+            beacon,
+            Beacon {
+                hop_index: 2,
+                offset_ms: 20,
+            }
+        );
+
+        clock.set(1000 + 50 * 12 + 25);
+        rx.synchronize(&beacon, 5);
+        for t in 0..1000 {
+            clock.set(clock.get() + t);
+            assert_eq!(rx.position(), tx.position());
+        }
+    }
+
+    #[test]
+    fn test_set_tuning_restores_autocalibration() {
+        let mut radio = radio();
+        radio.set_autocalibration(AutoCalibration::FromIdle).unwrap();
+        let mut hopper =
+            Hopper::new(Clock(0), HopSequence::explicit([3, 1, 2]), 100, None).unwrap();
+
+        hopper.calibrate(&mut radio, None, 10).unwrap();
+        assert_eq!(radio.get_autocalibration(), Ok(AutoCalibration::Disabled));
+        // Recalibrating keeps the setting to restore
+        hopper.calibrate(&mut radio, None, 10).unwrap();
+
+        hopper.start();
+        assert_eq!(hopper.poll(&mut radio), Ok(Some(3)));
+        assert_eq!(radio.0.spi.get(Config::FSCAL1), 3);
+
+        let tuning = Tuning::Frequency {
+            base_hz: 902_000_000,
+            spacing_hz: 200_000,
+        };
+        hopper.set_tuning(&mut radio, tuning).unwrap();
+        assert!(hopper.calibration().is_none());
+        assert_eq!(radio.get_autocalibration(), Ok(AutoCalibration::FromIdle));
+
+        // Nothing to restore without a calibration table
+        radio.set_autocalibration(AutoCalibration::ToIdle).unwrap();
+        hopper.set_tuning(&mut radio, Tuning::Channel).unwrap();
+        assert_eq!(radio.get_autocalibration(), Ok(AutoCalibration::ToIdle));
+    }
+}
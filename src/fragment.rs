@@ -0,0 +1,452 @@
+//! Fragmentation and reassembly of payloads larger than one packet.
+//!
+//! A [`Fragmenter`] splits a buffer into numbered fragments sized to fit a packet, and a
+//! [`Reassembler`] puts them back together into a caller-provided buffer. The receiver keeps a
+//! bitmap of received fragments and answers with a status message listing the missing ones, which
+//! the sender retransmits selectively. An empty status message acknowledges the whole transfer.
+//!
+//! Messages are carried by any packet transport, e.g. [`Link`](crate::link::Link) or
+//! [`Cc1101::transmit`](crate::Cc1101::transmit).
+
+use crate::PacketLength;
+
+const KIND_DATA: u8 = 0x01;
+const KIND_STATUS: u8 = 0x02;
+/// Transfer id, fragment index, fragment count and fragment size after the kind byte.
+pub const DATA_HEADER_SIZE: usize = 7;
+/// Transfer id and first fragment index of the bitmap after the kind byte.
+pub const STATUS_HEADER_SIZE: usize = 4;
+/// Missing fragment bitmap bytes in a status message, 8 fragments per byte.
+pub const STATUS_WINDOW: usize = 16;
+/// Largest status message.
+pub const STATUS_SIZE_MAX: usize = STATUS_HEADER_SIZE + STATUS_WINDOW;
+
+/// Fragmentation or reassembly failure.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum FragmentError {
+    /// Message too short, or of an unknown kind.
+    Malformed,
+    /// Transfer larger than the buffer, the fragment bitmap or 65535 fragments.
+    TooLarge,
+    /// Fragment size does not leave room for any payload.
+    PacketTooSmall,
+    /// Output buffer too small for the message.
+    BufferTooSmall,
+}
+
+/// Fragment payload size for packets of `length`, after `overhead` bytes used by the transport,
+/// e.g. the address byte.
+pub fn fragment_size(length: PacketLength, overhead: usize) -> usize {
+    let max = match length {
+        PacketLength::Fixed(len) | PacketLength::Variable(len) => len as usize,
+        PacketLength::Infinite => u8::MAX as usize,
+    };
+    max.saturating_sub(overhead + DATA_HEADER_SIZE)
+}
+
+/// Sending side of a transfer.
+pub struct Fragmenter<'a> {
+    data: &'a [u8],
+    transfer: u8,
+    size: usize,
+    count: u16,
+    next: u16,
+    retransmit_base: u16,
+    retransmit: [u8; STATUS_WINDOW],
+    complete: bool,
+}
+
+impl<'a> Fragmenter<'a> {
+    /// Split `data` into fragments of `size` payload bytes, see [`fragment_size`]. `transfer`
+    /// tells transfers apart, and should change for each one.
+    pub fn new(data: &'a [u8], transfer: u8, size: usize) -> Result<Self, FragmentError> {
+        if size == 0 || size > u8::MAX as usize {
+            return Err(FragmentError::PacketTooSmall);
+        }
+        let count = data.len().div_ceil(size).max(1);
+        let count = u16::try_from(count).map_err(|_| FragmentError::TooLarge)?;
+        Ok(Fragmenter {
+            data,
+            transfer,
+            size,
+            count,
+            next: 0,
+            retransmit_base: 0,
+            retransmit: [0; STATUS_WINDOW],
+            complete: false,
+        })
+    }
+
+    /// Number of fragments.
+    pub fn count(&self) -> u16 {
+        self.count
+    }
+
+    /// The receiver acknowledged the whole transfer.
+    pub fn is_complete(&self) -> bool {
+        self.complete
+    }
+
+    /// Write the next fragment to send into `out`, first all fragments in order, then those
+    /// reported missing. Returns the message length, or `None` while waiting for a status message.
+    pub fn next_fragment(&mut self, out: &mut [u8]) -> Result<Option<usize>, FragmentError> {
+        if self.next < self.count {
+            self.next += 1;
+            return self.fragment(self.next - 1, out).map(Some);
+        }
+        for (i, byte) in self.retransmit.iter_mut().enumerate() {
+            if *byte != 0 {
+                let bit = byte.leading_zeros() as u16;
+                *byte &= !(0x80 >> bit);
+                let index = self.retransmit_base + 8 * i as u16 + bit;
+                return self.fragment(index, out).map(Some);
+            }
+        }
+        Ok(None)
+    }
+
+    /// Write the last fragment into `out` again, so the receiver answers with a status message
+    /// when the previous one was lost.
+    pub fn last_fragment(&self, out: &mut [u8]) -> Result<usize, FragmentError> {
+        self.fragment(self.count - 1, out)
+    }
+
+    /// Process a status message from the receiver, scheduling the missing fragments for
+    /// retransmission. Returns whether the transfer is complete. Messages of other transfers
+    /// are ignored.
+    pub fn handle_status(&mut self, msg: &[u8]) -> Result<bool, FragmentError> {
+        let (transfer, base, bitmap) = match *msg {
+            [KIND_STATUS, transfer, base_hi, base_lo, ref bitmap @ ..]
+                if bitmap.len() <= STATUS_WINDOW =>
+            {
+                (transfer, u16::from_be_bytes([base_hi, base_lo]), bitmap)
+            }
+            _ => return Err(FragmentError::Malformed),
+        };
+        if transfer != self.transfer {
+            return Ok(self.complete);
+        }
+
+        self.complete = bitmap.is_empty();
+        self.retransmit_base = base;
+        self.retransmit = [0; STATUS_WINDOW];
+        for (i, (to, from)) in self.retransmit.iter_mut().zip(bitmap).enumerate() {
+            // Ignore bits beyond the last fragment
+            let first = base as usize + 8 * i;
+            let valid = (self.count as usize).saturating_sub(first).min(8);
+            *to = from & !(0xFFu8.checked_shr(valid as u32).unwrap_or(0));
+        }
+        Ok(self.complete)
+    }
+
+    fn fragment(&self, index: u16, out: &mut [u8]) -> Result<usize, FragmentError> {
+        let start = index as usize * self.size;
+        let payload =
+            &self.data[start.min(self.data.len())..(start + self.size).min(self.data.len())];
+        let len = DATA_HEADER_SIZE + payload.len();
+        let out = out.get_mut(..len).ok_or(FragmentError::BufferTooSmall)?;
+
+        let [index_hi, index_lo] = index.to_be_bytes();
+        let [count_hi, count_lo] = self.count.to_be_bytes();
+        out[..DATA_HEADER_SIZE].copy_from_slice(&[
+            KIND_DATA,
+            self.transfer,
+            index_hi,
+            index_lo,
+            count_hi,
+            count_lo,
+            self.size as u8,
+        ]);
+        out[DATA_HEADER_SIZE..].copy_from_slice(payload);
+        Ok(len)
+    }
+}
+
+/// Reassembly timing, in milliseconds.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ReassemblyConfig {
+    /// A transfer without fragments for this long is abandoned.
+    pub timeout_ms: u32,
+    /// A status message is due after no fragments for this long.
+    pub status_interval_ms: u32,
+}
+
+impl Default for ReassemblyConfig {
+    fn default() -> Self {
+        ReassemblyConfig {
+            timeout_ms: 5000,
+            status_interval_ms: 200,
+        }
+    }
+}
+
+/// Result of feeding a fragment.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Progress {
+    /// More fragments expected.
+    Incomplete,
+    /// The last fragment arrived with others missing, a status message should be sent.
+    StatusDue,
+    /// All fragments arrived, the transfer holds the given number of bytes. A status message
+    /// should be sent to acknowledge it.
+    Complete(usize),
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+struct Transfer {
+    id: u8,
+    count: u16,
+    size: usize,
+    len: usize,
+    received: u16,
+    last_ms: u32,
+}
+
+/// Receiving side, reassembling one transfer at a time into a buffer, with a bitmap of `B` bytes
+/// limiting a transfer to `8 * B` fragments.
+pub struct Reassembler<'a, const B: usize> {
+    buf: &'a mut [u8],
+    config: ReassemblyConfig,
+    bitmap: [u8; B],
+    transfer: Option<Transfer>,
+    last_status_ms: u32,
+}
+
+impl<'a, const B: usize> Reassembler<'a, B> {
+    pub fn new(buf: &'a mut [u8], config: ReassemblyConfig) -> Self {
+        Reassembler {
+            buf,
+            config,
+            bitmap: [0; B],
+            transfer: None,
+            last_status_ms: 0,
+        }
+    }
+
+    /// Reassembled data, once complete.
+    pub fn data(&self) -> Option<&[u8]> {
+        match self.transfer {
+            Some(t) if t.received == t.count => Some(&self.buf[..t.len]),
+            _ => None,
+        }
+    }
+
+    /// Id of the current transfer.
+    pub fn transfer(&self) -> Option<u8> {
+        self.transfer.map(|t| t.id)
+    }
+
+    /// Process a fragment received at `now_ms`. A fragment of another transfer abandons the
+    /// current one.
+    pub fn feed(&mut self, now_ms: u32, msg: &[u8]) -> Result<Progress, FragmentError> {
+        let (id, index, count, size, payload) = match *msg {
+            [KIND_DATA, id, index_hi, index_lo, count_hi, count_lo, size, ref payload @ ..] => (
+                id,
+                u16::from_be_bytes([index_hi, index_lo]),
+                u16::from_be_bytes([count_hi, count_lo]),
+                size as usize,
+                payload,
+            ),
+            _ => return Err(FragmentError::Malformed),
+        };
+        if index >= count {
+            return Err(FragmentError::Malformed);
+        }
+        let last = index + 1 == count;
+        if payload.len() > size || (!last && payload.len() != size) {
+            return Err(FragmentError::Malformed);
+        }
+
+        let mut transfer = match self.transfer {
+            Some(t) if t.id == id && t.count == count && t.size == size => t,
+            _ => {
+                if count as usize > 8 * B || (count as usize - 1) * size > self.buf.len() {
+                    self.transfer = None;
+                    return Err(FragmentError::TooLarge);
+                }
+                self.bitmap = [0; B];
+                self.last_status_ms = now_ms;
+                Transfer {
+                    id,
+                    count,
+                    size,
+                    len: 0,
+                    received: 0,
+                    last_ms: now_ms,
+                }
+            }
+        };
+        transfer.last_ms = now_ms;
+
+        let start = index as usize * size;
+        let end = start + payload.len();
+        if end > self.buf.len() {
+            self.transfer = None;
+            return Err(FragmentError::TooLarge);
+        }
+        let (byte, mask) = (index as usize / 8, 0x80 >> (index % 8));
+        if self.bitmap[byte] & mask == 0 {
+            self.bitmap[byte] |= mask;
+            self.buf[start..end].copy_from_slice(payload);
+            transfer.received += 1;
+        }
+        if last {
+            transfer.len = end;
+        }
+        self.transfer = Some(transfer);
+
+        Ok(if transfer.received == count {
+            Progress::Complete(transfer.len)
+        } else if last {
+            Progress::StatusDue
+        } else {
+            Progress::Incomplete
+        })
+    }
+
+    /// Write a status message into `out`, listing the missing fragments of the first window with
+    /// any missing, or none if complete. Returns the message length, or `None` without a transfer.
+    pub fn status(&mut self, now_ms: u32, out: &mut [u8]) -> Result<Option<usize>, FragmentError> {
+        let Some(transfer) = self.transfer else {
+            return Ok(None);
+        };
+        self.last_status_ms = now_ms;
+
+        let count = transfer.count as usize;
+        let missing = |index: usize| self.bitmap[index / 8] & (0x80 >> (index % 8)) == 0;
+        let (base, window) = match (0..count).find(|&i| missing(i)) {
+            Some(first) => {
+                let base = first / 8 * 8;
+                let bytes = (count - base).div_ceil(8).min(STATUS_WINDOW);
+                (base, bytes)
+            }
+            None => (count, 0),
+        };
+
+        let len = STATUS_HEADER_SIZE + window;
+        let out = out.get_mut(..len).ok_or(FragmentError::BufferTooSmall)?;
+        let [base_hi, base_lo] = (base as u16).to_be_bytes();
+        out[..STATUS_HEADER_SIZE].copy_from_slice(&[KIND_STATUS, transfer.id, base_hi, base_lo]);
+        for (i, byte) in out[STATUS_HEADER_SIZE..].iter_mut().enumerate() {
+            let first = base + 8 * i;
+            *byte = !self.bitmap[first / 8] & (0xFFu8 << (8 - (count - first).min(8)));
+        }
+        Ok(Some(len))
+    }
+
+    /// Check the transfer at `now_ms`, abandoning it after the timeout. Returns whether a status
+    /// message is due because no fragments arrived for the status interval.
+    pub fn poll(&mut self, now_ms: u32) -> bool {
+        let Some(transfer) = self.transfer else {
+            return false;
+        };
+        if transfer.received == transfer.count {
+            return false;
+        }
+        if now_ms.wrapping_sub(transfer.last_ms) >= self.config.timeout_ms {
+            self.transfer = None;
+            return false;
+        }
+        now_ms.wrapping_sub(transfer.last_ms) >= self.config.status_interval_ms
+            && now_ms.wrapping_sub(self.last_status_ms) >= self.config.status_interval_ms
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: ReassemblyConfig = ReassemblyConfig {
+        timeout_ms: 1000,
+        status_interval_ms: 100,
+    };
+
+    fn data() -> [u8; 100] {
+        core::array::from_fn(|i| i as u8)
+    }
+
+    #[test]
+    fn test_fragment_size() {
+        assert_eq!(fragment_size(PacketLength::Variable(61), 4), 50);
+        assert_eq!(fragment_size(PacketLength::Infinite, 0), 248);
+        assert_eq!(fragment_size(PacketLength::Fixed(8), 4), 0);
+    }
+
+    #[test]
+    fn test_selective_retransmit() {
+        let data = data();
+        let mut sender = Fragmenter::new(&data, 7, 8).unwrap();
+        assert_eq!(sender.count(), 13);
+
+        let mut buf = [0; 128];
+        let mut receiver = Reassembler::<2>::new(&mut buf, CONFIG);
+        let mut msg = [0; 32];
+
+        // Fragments 2 and 9 are lost
+        let mut index = 0;
+        while let Some(len) = sender.next_fragment(&mut msg).unwrap() {
+            let progress = match index {
+                2 | 9 => None,
+                _ => Some(receiver.feed(0, &msg[..len]).unwrap()),
+            };
+            if index == 12 {
+                assert_eq!(progress, Some(Progress::StatusDue));
+            }
+            index += 1;
+        }
+
+        let len = receiver.status(0, &mut msg).unwrap().unwrap();
+        assert_eq!(msg[..len], [KIND_STATUS, 7, 0, 0, 0b0010_0000, 0b0100_0000]);
+        assert!(!sender.handle_status(&msg[..len]).unwrap());
+
+        let mut progress = Progress::Incomplete;
+        let mut resent = 0;
+        while let Some(len) = sender.next_fragment(&mut msg).unwrap() {
+            progress = receiver.feed(10, &msg[..len]).unwrap();
+            resent += 1;
+        }
+        assert_eq!(resent, 2);
+        assert_eq!(progress, Progress::Complete(100));
+        assert_eq!(receiver.data(), Some(&data[..]));
+
+        let len = receiver.status(10, &mut msg).unwrap().unwrap();
+        assert_eq!(msg[..len], [KIND_STATUS, 7, 0, 13]);
+        assert!(sender.handle_status(&msg[..len]).unwrap());
+        assert!(sender.is_complete());
+    }
+
+    #[test]
+    fn test_timeout() {
+        let data = data();
+        let mut sender = Fragmenter::new(&data, 1, 50).unwrap();
+        let mut buf = [0; 100];
+        let mut receiver = Reassembler::<1>::new(&mut buf, CONFIG);
+        let mut msg = [0; 64];
+
+        let len = sender.next_fragment(&mut msg).unwrap().unwrap();
+        assert_eq!(receiver.feed(0, &msg[..len]), Ok(Progress::Incomplete));
+        assert!(!receiver.poll(50));
+        assert!(receiver.poll(100));
+        receiver.status(100, &mut msg).unwrap();
+        assert!(!receiver.poll(150));
+        assert!(receiver.poll(200));
+        assert!(!receiver.poll(1000));
+        assert_eq!(receiver.transfer(), None);
+    }
+
+    #[test]
+    fn test_too_large() {
+        let data = data();
+        let mut sender = Fragmenter::new(&data, 1, 10).unwrap();
+        let mut buf = [0; 50];
+        let mut receiver = Reassembler::<2>::new(&mut buf, CONFIG);
+        let mut msg = [0; 32];
+
+        let len = sender.next_fragment(&mut msg).unwrap().unwrap();
+        assert_eq!(receiver.feed(0, &msg[..len]), Err(FragmentError::TooLarge));
+        assert_eq!(receiver.feed(0, &msg[..3]), Err(FragmentError::Malformed));
+    }
+}
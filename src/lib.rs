@@ -2,31 +2,82 @@
 
 extern crate embedded_hal as hal;
 
-#[cfg(feature = "std")]
+#[cfg(any(feature = "std", test))]
 extern crate std;
 
 use core::fmt::{self, Display, Formatter};
 use hal::{digital::PinState, spi::SpiDevice};
 
+#[cfg(feature = "async")]
+pub mod asynch;
+pub mod calibration;
+pub mod codec;
+pub mod events;
+pub mod fhss;
+pub mod fragment;
+pub mod link;
 #[macro_use]
 pub mod lowlevel;
+#[cfg(test)]
+mod mock;
+pub mod ook;
+#[cfg(feature = "radio")]
+pub mod radio_traits;
+pub mod scrubber;
 mod types;
+pub mod typestate;
+pub mod watchdog;
 
-use lowlevel::{access::*, convert::*, registers::*};
-pub use lowlevel::{types::*, FIFO_SIZE_MAX};
+use codec::{FrameError, Framing};
+use lowlevel::{access::*, convert::*, registers::*, shadow::Shadow};
+pub use lowlevel::{types::*, FIFO_SIZE_MAX, PATABLE_SIZE};
 pub use types::*;
 
+/// Time CSn is held asserted before each SNOP while waiting for CHIP_RDYn after SLEEP.
+const WAKE_POLL_NS: u32 = 20_000;
+/// Maximum number of SNOP reads while waiting for CHIP_RDYn after SLEEP.
+const WAKE_POLLS: u8 = 100;
+
 /// CC1101 errors.
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Error<SpiE> {
     /// The TX FIFO buffer underflowed, too large packet for configured packet length.
     TxUnderflow,
     /// The RX FIFO buffer overflowed, too small buffer for configured packet length.
     RxOverflow,
+    /// RX FIFO overflow recovered by flushing, discarding the given number of incomplete packet bytes.
+    RxOverflowRecovered {
+        discarded: u8,
+    },
+    /// TX FIFO underflow recovered by flushing, the given number of bytes was not sent.
+    TxUnderflowRecovered {
+        unsent: u8,
+    },
     /// Corrupt packet received with invalid CRC.
     CrcMismatch,
     /// Invalid state read from MARCSTATE register
     InvalidState(u8),
+    /// Status register did not return the same value on two consecutive reads
+    UnstableStatus(Status),
+    /// Unknown chip, PARTNUM and VERSION do not match any supported chip variant
+    UnknownChip(u8, u8),
+    /// Operation not supported by the chip variant
+    NotSupported(Capability),
+    /// Configuration register read back differs from the written value
+    VerifyFailed {
+        reg: Config,
+        wrote: u8,
+        read: u8,
+    },
+    /// Chip did not signal CHIP_RDYn after waking up from SLEEP
+    ChipNotReady,
+    /// Radio did not reach the expected state within the poll limit
+    Timeout,
+    /// STX ignored in RX, clear channel assessment reported the channel busy
+    ChannelBusy,
+    /// GDO input pin error
+    Gdo(hal::digital::ErrorKind),
     /// User Input Error
     UserInputError(UserError),
     /// Platform-dependent SPI-errors, such as IO errors.
@@ -35,6 +86,7 @@ pub enum Error<SpiE> {
 
 /// Errors generated by user.
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum UserError {
     /// Missing Length parameter
     MissingLengthParameter,
@@ -44,6 +96,18 @@ pub enum UserError {
     MissingRxStatusParameters,
     /// Array too long
     ArrayTooLong(usize),
+    /// GDO signal not supported by the operation
+    UnsupportedGdoConfig(GdoCfg),
+    /// Channel not in the calibration table
+    UncalibratedChannel(u8),
+    /// Same GDO pin selected for two signals
+    GdoPinConflict(GdoPin),
+    /// Address reserved for broadcast
+    InvalidAddress(u8),
+    /// 32-bit sync word that is not a repetition of a 16-bit word
+    InvalidSyncWord(u32),
+    /// Sample period (in microseconds) outside the supported data rates
+    InvalidSamplePeriod(u32),
 }
 
 impl<SpiE> From<SpiE> for Error<SpiE> {
@@ -57,8 +121,28 @@ impl<SpiE: Display> Display for Error<SpiE> {
         match self {
             Self::TxUnderflow => write!(f, "TX FIFO buffer underflowed"),
             Self::RxOverflow => write!(f, "RX FIFO buffer overflowed"),
+            Self::RxOverflowRecovered {
+                discarded,
+            } => {
+                write!(f, "RX FIFO buffer overflowed, {} bytes discarded", discarded)
+            }
+            Self::TxUnderflowRecovered {
+                unsent,
+            } => {
+                write!(f, "TX FIFO buffer underflowed, {} bytes not sent", unsent)
+            }
             Self::CrcMismatch => write!(f, "CRC mismatch"),
             Self::InvalidState(s) => write!(f, "Invalid state: {}", s),
+            Self::UnstableStatus(r) => write!(f, "Unstable status register: {:?}", r),
+            Self::UnknownChip(p, v) => write!(f, "Unknown chip: partnum {}, version {}", p, v),
+            Self::NotSupported(c) => write!(f, "Not supported by chip variant: {:?}", c),
+            Self::VerifyFailed {
+                reg,
+                wrote,
+                read,
+            } => {
+                write!(f, "Verify failed: {:?} wrote {:#04x}, read {:#04x}", reg, wrote, read)
+            }
             Self::UserInputError(e) => match e {
                 UserError::MissingLengthParameter => {
                     write!(f, "User error: Missing length parameter")
@@ -70,7 +154,27 @@ impl<SpiE: Display> Display for Error<SpiE> {
                     write!(f, "User error: Missing Rx status parameters")
                 }
                 UserError::ArrayTooLong(v) => write!(f, "User error: Array too long: {}", v),
+                UserError::GdoPinConflict(p) => {
+                    write!(f, "User error: GDO pin selected twice: {:?}", p)
+                }
+                UserError::UncalibratedChannel(c) => {
+                    write!(f, "User error: Channel not calibrated: {}", c)
+                }
+                UserError::UnsupportedGdoConfig(c) => {
+                    write!(f, "User error: Unsupported GDO configuration: {:?}", c)
+                }
+                UserError::InvalidAddress(a) => write!(f, "User error: Invalid address: {}", a),
+                UserError::InvalidSyncWord(w) => {
+                    write!(f, "User error: Invalid sync word: {:#010x}", w)
+                }
+                UserError::InvalidSamplePeriod(us) => {
+                    write!(f, "User error: Invalid sample period: {} us", us)
+                }
             },
+            Self::ChipNotReady => write!(f, "Chip not ready"),
+            Self::Timeout => write!(f, "Timeout"),
+            Self::ChannelBusy => write!(f, "Channel busy"),
+            Self::Gdo(e) => write!(f, "GDO pin error: {}", e),
             Self::Spi(e) => write!(f, "SPI error: {}", e),
         }
     }
@@ -80,7 +184,10 @@ impl<SpiE: Display> Display for Error<SpiE> {
 impl<SpiE: Display + core::fmt::Debug> std::error::Error for Error<SpiE> {}
 
 /// High level API for interacting with the CC1101 radio chip.
-pub struct Cc1101<SPI>(lowlevel::Cc1101<SPI>);
+///
+/// The status observer `O` is called with the chip status byte returned by every SPI transaction,
+/// see [`Cc1101::with_status_observer`].
+pub struct Cc1101<SPI, O = fn(StatusByte)>(lowlevel::Cc1101<SPI, O>);
 
 impl<SPI, SpiE> Cc1101<SPI>
 where
@@ -89,12 +196,112 @@ where
     pub fn new(spi: SPI) -> Result<Self, Error<SpiE>> {
         Ok(Cc1101(lowlevel::Cc1101::new(spi)?))
     }
+}
+
+impl<SPI, SpiE, O> Cc1101<SPI, O>
+where
+    SPI: SpiDevice<u8, Error = SpiE>,
+    O: FnMut(StatusByte),
+{
+    /// Create the driver with a closure called with the chip status byte returned by every SPI
+    /// transaction. The closure may capture state, e.g. to count the status bytes reporting a
+    /// FIFO error.
+    pub fn with_status_observer(spi: SPI, observer: O) -> Result<Self, Error<SpiE>> {
+        Ok(Cc1101(lowlevel::Cc1101::with_status_observer(spi, Some(observer))?))
+    }
+
+    /// Maximum number of state reads while waiting for the radio, e.g. for a transmission to
+    /// complete, before failing with `Error::Timeout`. The time a read takes depends on the SPI
+    /// clock, so the limit must cover the longest packet at the configured data rate.
+    pub fn set_poll_limit(&mut self, polls: u32) {
+        self.0.poll_limit = polls;
+    }
+
+    /// Read back and compare every configuration register write, failing with `Error::VerifyFailed`
+    /// on mismatch.
+    pub fn write_verification_enable(&mut self, enable: bool) {
+        self.0.verify = enable;
+    }
+
+    /// Maximum number of additional reads of a status register, waiting for two consecutive reads
+    /// to match, before failing with `Error::UnstableStatus`.
+    pub fn set_status_read_retries(&mut self, retries: u8) {
+        self.0.status_retries = retries;
+    }
+
+    /// Keep a shadow copy of the configuration registers in RAM. Register reads and modifications
+    /// are served from the copy, and the modified registers are written to the chip with burst
+    /// access before the next command strobe, or by [`Cc1101::flush_registers`].
+    pub fn shadow_registers_enable(&mut self, init: ShadowInit) -> Result<(), Error<SpiE>> {
+        let shadow = match init {
+            ShadowInit::ResetValues => Shadow::from_reset_values(),
+            ShadowInit::ReadChip => {
+                let mut values = [0; Config::ALL.len()];
+                self.0.read_burst(Config::IOCFG2, &mut values)?;
+                Shadow::from_values(values)
+            }
+        };
+        self.0.shadow = Some(shadow);
+        Ok(())
+    }
+
+    /// Write the modified registers to the chip, and stop using the shadow copy.
+    pub fn shadow_registers_disable(&mut self) -> Result<(), Error<SpiE>> {
+        self.0.flush_shadow()?;
+        self.0.shadow = None;
+        Ok(())
+    }
+
+    /// Write the registers modified in the shadow copy to the chip.
+    pub fn flush_registers(&mut self) -> Result<(), Error<SpiE>> {
+        self.0.flush_shadow()
+    }
+
+    /// Replace or remove the status observer, see [`Cc1101::with_status_observer`].
+    pub fn set_status_observer(&mut self, observer: Option<O>) {
+        self.0.status_observer = observer;
+    }
 
     /// Last Chip Status Byte
     pub fn get_chip_status(&mut self) -> Option<StatusByte> {
         self.0.status
     }
 
+    /// Identify the chip variant from its part number and version, see [`ChipVariant`].
+    pub fn identify_chip(&mut self) -> Result<ChipVariant, Error<SpiE>> {
+        let (partnum, version) = self.get_hw_info()?;
+
+        let variant = ChipVariant::try_from((partnum, version))
+            .map_err(|ChipVariantError::UnknownChip(p, v)| Error::UnknownChip(p, v))?;
+        self.0.variant = variant;
+        Ok(variant)
+    }
+
+    /// Select the chip variant explicitly, e.g. for value line parts that cannot be identified.
+    pub fn set_chip_variant(&mut self, variant: ChipVariant) {
+        self.0.variant = variant;
+    }
+
+    /// Chip variant in use, CC1101 unless identified or selected otherwise.
+    pub fn get_chip_variant(&self) -> ChipVariant {
+        self.0.variant
+    }
+
+    /// Features supported by the chip variant in use. The command strobes and FIFO accesses of a
+    /// missing feature fail with `Error::NotSupported`. Register accesses are not checked, all
+    /// variants share the register map.
+    pub fn get_capabilities(&self) -> Capabilities {
+        self.0.variant.capabilities()
+    }
+
+    fn require(&self, capability: Capability) -> Result<(), Error<SpiE>> {
+        if self.get_capabilities().supports(capability) {
+            Ok(())
+        } else {
+            Err(Error::NotSupported(capability))
+        }
+    }
+
     /// Command Strobe: Reset chip
     pub fn reset_chip(&mut self) -> Result<(), Error<SpiE>> {
         self.0.write_cmd_strobe(Command::SRES)?;
@@ -121,12 +328,14 @@ where
 
     /// Command Strobe: Enable RX
     pub fn enable_rx(&mut self) -> Result<(), Error<SpiE>> {
+        self.require(Capability::Receive)?;
         self.0.write_cmd_strobe(Command::SRX)?;
         Ok(())
     }
 
     /// Command Strobe: Enable TX
     pub fn enable_tx(&mut self) -> Result<(), Error<SpiE>> {
+        self.require(Capability::Transmit)?;
         self.0.write_cmd_strobe(Command::STX)?;
         Ok(())
     }
@@ -139,6 +348,7 @@ where
 
     /// Command Strobe: Start automatic RX polling sequence (Wake-on-Radio)
     pub fn start_wake_on_radio(&mut self) -> Result<(), Error<SpiE>> {
+        self.require(Capability::WakeOnRadio)?;
         self.0.write_cmd_strobe(Command::SWOR)?;
         Ok(())
     }
@@ -149,6 +359,49 @@ where
         Ok(())
     }
 
+    /// Enter SLEEP, saving the PATABLE and TEST2..TEST0 registers which are not retained.
+    /// The chip stays in SLEEP until [`Cc1101::wake`].
+    pub fn sleep(&mut self) -> Result<(), Error<SpiE>> {
+        self.exit_rx_tx()?;
+        self.await_machine_state(MachineState::IDLE)?;
+
+        let mut context = SleepContext {
+            patable: [0; PATABLE_SIZE],
+            test: [0; 3],
+        };
+        self.0.access_patable(Access::Read, &mut context.patable)?;
+        self.0.read_burst(Config::TEST2, &mut context.test)?;
+        self.0.sleep_context = Some(context);
+
+        self.enter_power_down_mode()
+    }
+
+    /// Wake up from SLEEP by asserting CSn and waiting for CHIP_RDYn, then restore the registers
+    /// saved by [`Cc1101::sleep`], and optionally calibrate the frequency synthesizer.
+    /// The radio is left in IDLE.
+    pub fn wake(&mut self, calibrate: bool) -> Result<(), Error<SpiE>> {
+        let mut ready = false;
+        for _ in 0..WAKE_POLLS {
+            if self.0.read_chip_status_delayed(WAKE_POLL_NS)?.chip_rdy {
+                ready = true;
+                break;
+            }
+        }
+        if !ready {
+            return Err(Error::ChipNotReady);
+        }
+
+        if let Some(mut context) = self.0.sleep_context.take() {
+            self.0.write_burst(Config::TEST2, &context.test)?;
+            self.0.access_patable(Access::Write, &mut context.patable)?;
+        }
+
+        if calibrate {
+            self.cal_freq_synth_and_turn_off()?;
+        }
+        self.await_machine_state(MachineState::IDLE)
+    }
+
     /// Command Strobe: Flush the RX FIFO buffer
     pub fn flush_rx_fifo_buffer(&mut self) -> Result<(), Error<SpiE>> {
         self.0.write_cmd_strobe(Command::SFRX)?;
@@ -163,6 +416,7 @@ where
 
     /// Command Strobe: Reset real time clock to Event1 value
     pub fn reset_rtc_to_event1(&mut self) -> Result<(), Error<SpiE>> {
+        self.require(Capability::WakeOnRadio)?;
         self.0.write_cmd_strobe(Command::SWORRST)?;
         Ok(())
     }
@@ -233,6 +487,26 @@ where
         Ok(())
     }
 
+    /// Set the Output Pin Configuration of GDO0 or GDO2
+    pub fn set_gdo_config(&mut self, pin: GdoPin, config: GdoCfg) -> Result<(), Error<SpiE>> {
+        match pin {
+            GdoPin::Gdo0 => self.set_gdo0_config(config),
+            GdoPin::Gdo2 => self.set_gdo2_config(config),
+        }
+    }
+
+    /// Set the Output Pin Active State of GDO0 or GDO2
+    pub fn set_gdo_active_state(
+        &mut self,
+        pin: GdoPin,
+        state: PinState,
+    ) -> Result<(), Error<SpiE>> {
+        match pin {
+            GdoPin::Gdo0 => self.set_gdo0_active_state(state),
+            GdoPin::Gdo2 => self.set_gdo2_active_state(state),
+        }
+    }
+
     /// Enable analog temperature sensor
     pub fn temperature_sensor_enable(&mut self, enable: bool) -> Result<(), Error<SpiE>> {
         match enable {
@@ -262,6 +536,45 @@ where
         Ok(())
     }
 
+    /// Set the threshold for the TX FIFO and RX FIFO.
+    pub fn set_fifo_threshold(&mut self, threshold: FifoThreshold) -> Result<(), Error<SpiE>> {
+        self.0.modify_register(Config::FIFOTHR, |r| {
+            FIFOTHR(r).modify().fifo_thr(threshold.into()).bits()
+        })?;
+        Ok(())
+    }
+
+    /// Read the threshold for the TX FIFO and RX FIFO.
+    pub fn get_fifo_threshold(&mut self) -> Result<FifoThreshold, Error<SpiE>> {
+        Ok(FIFOTHR(self.0.read_register(Config::FIFOTHR)?).fifo_thr().into())
+    }
+
+    /// Set the RX attenuation, used to avoid saturation when close to the transmitter (DN010).
+    pub fn set_close_in_rx(&mut self, attenuation: CloseInRx) -> Result<(), Error<SpiE>> {
+        self.0.modify_register(Config::FIFOTHR, |r| {
+            FIFOTHR(r).modify().close_in_rx(attenuation.into()).bits()
+        })?;
+        Ok(())
+    }
+
+    /// Read the RX attenuation.
+    pub fn get_close_in_rx(&mut self) -> Result<CloseInRx, Error<SpiE>> {
+        Ok(FIFOTHR(self.0.read_register(Config::FIFOTHR)?).close_in_rx().into())
+    }
+
+    /// Retain the ADC settings in SLEEP, required to use a RX filter bandwidth below 325 kHz after wake-up.
+    pub fn adc_retention_enable(&mut self, enable: bool) -> Result<(), Error<SpiE>> {
+        self.0.modify_register(Config::FIFOTHR, |r| {
+            FIFOTHR(r).modify().adc_retention(enable as u8).bits()
+        })?;
+        Ok(())
+    }
+
+    /// Read whether ADC retention is enabled.
+    pub fn is_adc_retention_enabled(&mut self) -> Result<bool, Error<SpiE>> {
+        Ok(FIFOTHR(self.0.read_register(Config::FIFOTHR)?).adc_retention() == 1)
+    }
+
     /// Sets the carrier frequency (in Hertz).
     pub fn set_frequency(&mut self, hz: u64) -> Result<(), Error<SpiE>> {
         let (freq0, freq1, freq2) = from_frequency(hz);
@@ -271,6 +584,34 @@ where
         Ok(())
     }
 
+    /// Select the channel, multiplied by the channel spacing and added to the base frequency.
+    pub fn set_channel(&mut self, channel: u8) -> Result<(), Error<SpiE>> {
+        self.0.write_register(Config::CHANNR, CHANNR::default().chan(channel).bits())?;
+        Ok(())
+    }
+
+    /// Read the selected channel.
+    pub fn get_channel(&mut self) -> Result<u8, Error<SpiE>> {
+        Ok(CHANNR(self.0.read_register(Config::CHANNR)?).chan())
+    }
+
+    /// Read the frequency synthesizer calibration results.
+    pub fn get_fs_calibration(&mut self) -> Result<FsCal, Error<SpiE>> {
+        let mut values = [0; 3];
+        self.0.read_burst(Config::FSCAL3, &mut values)?;
+        Ok(FsCal {
+            fscal3: values[0],
+            fscal2: values[1],
+            fscal1: values[2],
+        })
+    }
+
+    /// Write frequency synthesizer calibration results captured earlier, instead of calibrating.
+    /// Only valid in IDLE, for the frequency they were captured at.
+    pub fn set_fs_calibration(&mut self, cal: FsCal) -> Result<(), Error<SpiE>> {
+        self.0.write_burst(Config::FSCAL3, &[cal.fscal3, cal.fscal2, cal.fscal1])
+    }
+
     /// Sets the frequency synthesizer intermediate frequency (in Hertz).
     pub fn set_freq_if(&mut self, hz: u64) -> Result<(), Error<SpiE>> {
         self.0
@@ -302,6 +643,50 @@ where
         Ok(())
     }
 
+    /// Read the automatic calibration setting.
+    pub fn get_autocalibration(&mut self) -> Result<AutoCalibration, Error<SpiE>> {
+        Ok(MCSM0(self.0.read_register(Config::MCSM0)?).fs_autocal().into())
+    }
+
+    /// Programs the number of times the ripple counter must expire before CHIP_RDYn goes low.
+    pub fn set_power_on_timeout(&mut self, timeout: PoTimeout) -> Result<(), Error<SpiE>> {
+        self.0.modify_register(Config::MCSM0, |r| {
+            MCSM0(r).modify().po_timeout(timeout.into()).bits()
+        })?;
+        Ok(())
+    }
+
+    /// Read the power-on timeout.
+    pub fn get_power_on_timeout(&mut self) -> Result<PoTimeout, Error<SpiE>> {
+        Ok(MCSM0(self.0.read_register(Config::MCSM0)?).po_timeout().into())
+    }
+
+    /// Enable the pin radio control option, state transitions controlled by CSn, SCLK and SI.
+    pub fn pin_ctrl_enable(&mut self, enable: bool) -> Result<(), Error<SpiE>> {
+        self.0.modify_register(Config::MCSM0, |r| {
+            MCSM0(r).modify().pin_ctrl_en(enable as u8).bits()
+        })?;
+        Ok(())
+    }
+
+    /// Read whether the pin radio control option is enabled.
+    pub fn is_pin_ctrl_enabled(&mut self) -> Result<bool, Error<SpiE>> {
+        Ok(MCSM0(self.0.read_register(Config::MCSM0)?).pin_ctrl_en() == 1)
+    }
+
+    /// Force the XOSC to stay on in the SLEEP state.
+    pub fn xosc_force_on_enable(&mut self, enable: bool) -> Result<(), Error<SpiE>> {
+        self.0.modify_register(Config::MCSM0, |r| {
+            MCSM0(r).modify().xosc_force_on(enable as u8).bits()
+        })?;
+        Ok(())
+    }
+
+    /// Read whether the XOSC is forced on in the SLEEP state.
+    pub fn is_xosc_force_on_enabled(&mut self) -> Result<bool, Error<SpiE>> {
+        Ok(MCSM0(self.0.read_register(Config::MCSM0)?).xosc_force_on() == 1)
+    }
+
     /// Set Modem deviation setting.
     pub fn set_deviation(&mut self, deviation: u64) -> Result<(), Error<SpiE>> {
         let (mantissa, exponent) = from_deviation(deviation);
@@ -329,6 +714,14 @@ where
         Ok(())
     }
 
+    /// Enable Manchester encoding/decoding
+    pub fn manchester_enable(&mut self, enable: bool) -> Result<(), Error<SpiE>> {
+        self.0.modify_register(Config::MDMCFG2, |r| {
+            MDMCFG2(r).modify().manchester_en(enable as u8).bits()
+        })?;
+        Ok(())
+    }
+
     /// Sets the minimum number of preamble bytes to be transmitted
     pub fn set_num_preamble(&mut self, num_preamble: NumPreamble) -> Result<(), Error<SpiE>> {
         self.0.modify_register(Config::MDMCFG1, |r| {
@@ -337,6 +730,30 @@ where
         Ok(())
     }
 
+    /// Select what should happen when a packet has been received.
+    pub fn set_rxoff_mode(&mut self, mode: RxOffMode) -> Result<(), Error<SpiE>> {
+        self.0
+            .modify_register(Config::MCSM1, |r| MCSM1(r).modify().rxoff_mode(mode.into()).bits())?;
+        Ok(())
+    }
+
+    /// Read what should happen when a packet has been received.
+    pub fn get_rxoff_mode(&mut self) -> Result<RxOffMode, Error<SpiE>> {
+        Ok(MCSM1(self.0.read_register(Config::MCSM1)?).rxoff_mode().into())
+    }
+
+    /// Select what should happen when a packet has been sent.
+    pub fn set_txoff_mode(&mut self, mode: TxOffMode) -> Result<(), Error<SpiE>> {
+        self.0
+            .modify_register(Config::MCSM1, |r| MCSM1(r).modify().txoff_mode(mode.into()).bits())?;
+        Ok(())
+    }
+
+    /// Read what should happen when a packet has been sent.
+    pub fn get_txoff_mode(&mut self) -> Result<TxOffMode, Error<SpiE>> {
+        Ok(MCSM1(self.0.read_register(Config::MCSM1)?).txoff_mode().into())
+    }
+
     /// Selects CCA_MODE; Reflected in CCA signal.
     pub fn set_cca_mode(&mut self, cca_mode: CcaMode) -> Result<(), Error<SpiE>> {
         self.0.modify_register(Config::MCSM1, |r| {
@@ -355,14 +772,27 @@ where
     }
 
     /// Configure the sync word to use, and at what level it should be verified.
+    /// A 32-bit sync word whose halves differ fails with `UserError::InvalidSyncWord`.
     pub fn set_sync_mode(&mut self, sync_mode: SyncMode) -> Result<(), Error<SpiE>> {
         let reset: u16 = (SYNC1::default().bits() as u16) << 8 | (SYNC0::default().bits() as u16);
+        let repeated = |word: u32| match ((word >> 16) as u16, word as u16) {
+            (high, low) if high == low => Ok(low),
+            _ => Err(Error::UserInputError(UserError::InvalidSyncWord(word))),
+        };
 
         let (mode, word) = match sync_mode {
             SyncMode::Disabled => (SyncCheck::DISABLED, reset),
             SyncMode::MatchPartial(word) => (SyncCheck::CHECK_15_16, word),
             SyncMode::MatchPartialRepeated(word) => (SyncCheck::CHECK_30_32, word),
             SyncMode::MatchFull(word) => (SyncCheck::CHECK_16_16, word),
+            SyncMode::MatchPartial32(word) => (SyncCheck::CHECK_30_32, repeated(word)?),
+            SyncMode::CarrierSense => (SyncCheck::CHECK_0_0_CS, reset),
+            SyncMode::MatchPartialCarrierSense(word) => (SyncCheck::CHECK_15_16_CS, word),
+            SyncMode::MatchPartialRepeatedCarrierSense(word) => (SyncCheck::CHECK_30_32_CS, word),
+            SyncMode::MatchFullCarrierSense(word) => (SyncCheck::CHECK_16_16_CS, word),
+            SyncMode::MatchPartial32CarrierSense(word) => {
+                (SyncCheck::CHECK_30_32_CS, repeated(word)?)
+            }
         };
         self.0.modify_register(Config::MDMCFG2, |r| {
             MDMCFG2(r).modify().sync_mode(mode.into()).bits()
@@ -372,6 +802,31 @@ where
         Ok(())
     }
 
+    /// Sets the absolute RSSI threshold for asserting carrier sense, in dB relative to MAGN_TARGET.
+    /// The threshold is limited to -7..=7 dB, `None` disables the absolute threshold.
+    pub fn set_carrier_sense_abs_threshold(&mut self, db: Option<i8>) -> Result<(), Error<SpiE>> {
+        let threshold = match db {
+            // 0b1000 (-8) disables the absolute threshold
+            None => 0b1000,
+            Some(db) => (db.clamp(-7, 7) as u8) & 0x0f,
+        };
+        self.0.modify_register(Config::AGCCTRL1, |r| {
+            AGCCTRL1(r).modify().carrier_sense_abs_thr(threshold).bits()
+        })?;
+        Ok(())
+    }
+
+    /// Sets the relative change threshold for asserting carrier sense.
+    pub fn set_carrier_sense_rel_threshold(
+        &mut self,
+        threshold: CarrierSenseRelThr,
+    ) -> Result<(), Error<SpiE>> {
+        self.0.modify_register(Config::AGCCTRL1, |r| {
+            AGCCTRL1(r).modify().carrier_sense_rel_thr(threshold.into()).bits()
+        })?;
+        Ok(())
+    }
+
     /// Set the modulation format of the radio signal.
     pub fn set_modulation_format(
         &mut self,
@@ -383,6 +838,19 @@ where
         Ok(())
     }
 
+    /// Sets the preamble quality estimator threshold (0..=7), sync word is only accepted when the
+    /// preamble quality is above 4 times the threshold. Zero disables the preamble quality check.
+    pub fn set_preamble_quality_threshold(&mut self, pqt: u8) -> Result<(), Error<SpiE>> {
+        self.0
+            .modify_register(Config::PKTCTRL1, |r| PKTCTRL1(r).modify().pqt(pqt.min(7)).bits())?;
+        Ok(())
+    }
+
+    /// Read the preamble quality estimator threshold.
+    pub fn get_preamble_quality_threshold(&mut self) -> Result<u8, Error<SpiE>> {
+        Ok(PKTCTRL1(self.0.read_register(Config::PKTCTRL1)?).pqt())
+    }
+
     /// Enable automatic flush of RX FIFO when CRC is not OK.
     /// This requires that only one packet is in the RX FIFO and that packet length is limited to the RX FIFO size.
     pub fn crc_autoflush_enable(&mut self, enable: bool) -> Result<(), Error<SpiE>> {
@@ -439,8 +907,63 @@ where
         Ok(())
     }
 
+    /// Select the format of RX and TX data.
+    pub fn set_packet_format(&mut self, format: PacketFormat) -> Result<(), Error<SpiE>> {
+        self.0.modify_register(Config::PKTCTRL0, |r| {
+            PKTCTRL0(r).modify().pkt_format(format.into()).bits()
+        })?;
+        Ok(())
+    }
+
+    /// Read the format of RX and TX data.
+    pub fn get_packet_format(&mut self) -> Result<PacketFormat, Error<SpiE>> {
+        Ok(PKTCTRL0(self.0.read_register(Config::PKTCTRL0)?).pkt_format().into())
+    }
+
+    /// Configure asynchronous serial mode: TX data is sampled on GDO0, RX data is output on
+    /// `data_out` without any data decision or synchronization on chip.
+    /// The packet handling features are not supported in this mode, sync word detection, CRC,
+    /// data whitening, FEC and Manchester encoding are disabled.
+    pub fn set_asynchronous_serial_mode(&mut self, data_out: GdoPin) -> Result<(), Error<SpiE>> {
+        self.set_sync_mode(SyncMode::Disabled)?;
+        self.crc_enable(false)?;
+        self.white_data_enable(false)?;
+        self.fec_enable(false)?;
+        self.manchester_enable(false)?;
+        self.set_gdo_config(data_out, GdoCfg::SERIAL_DATA_OUT)?;
+        self.set_packet_format(PacketFormat::AsynchronousSerial)
+    }
+
+    /// Configure synchronous serial mode: the radio outputs the data clock on `clock`, TX data is
+    /// sampled on GDO0, and RX data is output on `data_out`.
+    /// Preamble and sync word are inserted and detected as set by `sync_mode`. CRC and data
+    /// whitening are disabled, frames with a software CRC and whitening can be built with
+    /// [`codec::Framing`]. FEC is also disabled when the sync word is, as required by the
+    /// datasheet.
+    pub fn set_synchronous_serial_mode(
+        &mut self,
+        clock: GdoPin,
+        data_out: GdoPin,
+        sync_mode: SyncMode,
+    ) -> Result<(), Error<SpiE>> {
+        if clock == data_out {
+            return Err(Error::UserInputError(UserError::GdoPinConflict(clock)));
+        }
+
+        self.set_sync_mode(sync_mode)?;
+        self.crc_enable(false)?;
+        self.white_data_enable(false)?;
+        if sync_mode == SyncMode::Disabled {
+            self.fec_enable(false)?;
+        }
+        self.set_gdo_config(clock, GdoCfg::SERIAL_CLOCK)?;
+        self.set_gdo_config(data_out, GdoCfg::SERIAL_SYNC_DATA_OUT)?;
+        self.set_packet_format(PacketFormat::SynchronousSerial)
+    }
+
     /// Configure packet mode, and length.
     pub fn set_packet_length(&mut self, length: PacketLength) -> Result<(), Error<SpiE>> {
+        self.0.length_field = false;
         let (format, pktlen) = match length {
             PacketLength::Fixed(limit) => (LengthConfig::FIXED, limit),
             PacketLength::Variable(max_limit) => {
@@ -463,11 +986,33 @@ where
         Ok((partnum, version))
     }
 
+    /// Read all configuration registers and the power amplifier table.
+    pub fn read_config_image(&mut self) -> Result<ConfigImage, Error<SpiE>> {
+        let mut image = ConfigImage {
+            config: [0; Config::ALL.len()],
+            patable: [0; PATABLE_SIZE],
+        };
+        self.0.read_burst(Config::IOCFG2, &mut image.config)?;
+        self.0.access_patable(Access::Read, &mut image.patable)?;
+        Ok(image)
+    }
+
+    /// Write all configuration registers and the power amplifier table.
+    /// The calibration results in FSCAL3..FSCAL1 are left untouched.
+    pub fn write_config_image(&mut self, image: &ConfigImage) -> Result<(), Error<SpiE>> {
+        let (fscal_first, fscal_last) = (Config::FSCAL3 as usize, Config::FSCAL1 as usize);
+        self.0.write_burst(Config::IOCFG2, &image.config[..fscal_first])?;
+        self.0.write_burst(Config::FSCAL0, &image.config[fscal_last + 1..])?;
+        let mut patable = image.patable;
+        self.0.access_patable(Access::Write, &mut patable)?;
+        Ok(())
+    }
+
     /// Read Frequency Offset Estimate from Demodulator
     /// Frequency offset compensation is only supported for 2-FSK, GFSK, 4-FSK, and MSK modulation.
     /// This register will read 0 when using ASK or OOK modulation.
     pub fn get_est_freq_offset(&mut self) -> Result<i32, Error<SpiE>> {
-        Ok(to_frequency_offset(self.0.read_register(Status::FREQEST)?))
+        Ok(to_frequency_offset(self.0.read_status_stable(Status::FREQEST)?))
     }
 
     /// Read Link Quality Indicator.
@@ -478,12 +1023,33 @@ where
 
     /// Received Signal Strength Indicator is an estimate of the signal power level in the chosen channel.
     pub fn get_rssi_dbm(&mut self) -> Result<i16, Error<SpiE>> {
-        Ok(from_rssi_to_rssi_dbm(self.0.read_register(Status::RSSI)?))
+        Ok(from_rssi_to_rssi_dbm(self.0.read_status_stable(Status::RSSI)?))
+    }
+
+    /// Read the Wake-on-Radio timer value. The low byte may roll over between the reads of the
+    /// two registers, so both are read until two consecutive values match.
+    pub fn get_wor_time(&mut self) -> Result<u16, Error<SpiE>> {
+        let mut last = self.read_wor_time()?;
+
+        for _ in 0..self.0.status_retries {
+            let value = self.read_wor_time()?;
+            if value == last {
+                return Ok(value);
+            }
+            last = value;
+        }
+        Err(Error::UnstableStatus(Status::WORTIME0))
+    }
+
+    fn read_wor_time(&mut self) -> Result<u16, Error<SpiE>> {
+        let high = WORTIME1(self.0.read_register(Status::WORTIME1)?).time();
+        let low = WORTIME0(self.0.read_register(Status::WORTIME0)?).time();
+        Ok((high as u16) << 8 | low as u16)
     }
 
     /// Read the Machine State
     pub fn get_machine_state(&mut self) -> Result<MachineState, Error<SpiE>> {
-        let marcstate = MARCSTATE(self.0.read_register(Status::MARCSTATE)?);
+        let marcstate = MARCSTATE(self.0.read_status_stable(Status::MARCSTATE)?);
 
         match MachineState::try_from(marcstate.marc_state()) {
             Ok(state) => Ok(state),
@@ -500,7 +1066,7 @@ where
 
     /// Read number of bytes in TX FIFO
     pub fn get_tx_bytes(&mut self) -> Result<u8, Error<SpiE>> {
-        let txbytes = TXBYTES(self.0.read_register(Status::TXBYTES)?);
+        let txbytes = TXBYTES(self.0.read_status_stable(Status::TXBYTES)?);
         let num_txbytes: u8 = txbytes.num_txbytes();
 
         if txbytes.txfifo_underflow() != 0 {
@@ -512,7 +1078,7 @@ where
 
     /// Read number of bytes in RX FIFO
     pub fn get_rx_bytes(&mut self) -> Result<u8, Error<SpiE>> {
-        let rxbytes = RXBYTES(self.0.read_register(Status::RXBYTES)?);
+        let rxbytes = RXBYTES(self.0.read_status_stable(Status::RXBYTES)?);
         let num_rxbytes: u8 = rxbytes.num_rxbytes();
 
         if rxbytes.rxfifo_overflow() != 0 {
@@ -531,6 +1097,8 @@ where
         lqi: &mut Option<u8>,
         data: &mut [u8],
     ) -> Result<(), Error<SpiE>> {
+        self.require(Capability::Receive)?;
+
         let num_of_optional_fields = self.0.length_field as usize + self.0.address_field as usize;
         let data_len_max: usize = FIFO_SIZE_MAX as usize - num_of_optional_fields;
         let mut optional_fields = [0, 0];
@@ -588,6 +1156,8 @@ where
         address: &mut Option<u8>,
         data: &mut [u8],
     ) -> Result<(), Error<SpiE>> {
+        self.require(Capability::Transmit)?;
+
         let num_of_optional_fields = self.0.length_field as usize + self.0.address_field as usize;
         let data_len_max: usize = FIFO_SIZE_MAX as usize - num_of_optional_fields;
         let mut optional_fields = [0, 0];
@@ -624,23 +1194,22 @@ where
         Ok(())
     }
 
-    /// ------------------------------------------------------------------------
-    /// TODO: The functions bellow shall be discontinued in the future.
-    /// 1.  await_machine_state() is a blocking function
-    /// 2.  set_defaults() was written with specific application in mind
-    /// 3.  set_radio_mode() depends on await_machine_state which is blocking
-    /// 4.  receive() was written with specific application in mind
-    /// 5.  set_raw_mode() was written with specific application in mind
-    /// ------------------------------------------------------------------------
+    // ------------------------------------------------------------------------
+    // TODO: The functions bellow shall be discontinued in the future.
+    // 1.  await_machine_state() is a blocking function
+    // 2.  set_defaults() was written with specific application in mind
+    // 3.  set_radio_mode() depends on await_machine_state which is blocking
+    // 4.  receive() was written with specific application in mind
+    // 5.  set_raw_mode() was written with specific application in mind
+    // ------------------------------------------------------------------------
 
     fn await_machine_state(&mut self, target_state: MachineState) -> Result<(), Error<SpiE>> {
-        loop {
-            let machine_state = self.get_machine_state()?;
-            if target_state == machine_state {
-                break;
+        for _ in 0..self.0.poll_limit {
+            if self.get_machine_state()? == target_state {
+                return Ok(());
             }
         }
-        Ok(())
+        Err(Error::Timeout)
     }
 
     /// Configure some default settings, to be removed in the future.
@@ -672,11 +1241,8 @@ where
                 self.exit_rx_tx()?;
                 MachineState::IDLE
             }
-            RadioMode::Sleep => {
-                self.set_radio_mode(RadioMode::Idle)?;
-                self.enter_power_down_mode()?;
-                MachineState::SLEEP
-            }
+            // Reading MARCSTATE would wake the chip up again
+            RadioMode::Sleep => return self.sleep(),
             RadioMode::Calibrate => {
                 self.set_radio_mode(RadioMode::Idle)?;
                 self.cal_freq_synth_and_turn_off()?;
@@ -696,26 +1262,28 @@ where
         self.await_machine_state(target)
     }
 
-    fn rx_bytes_available(&mut self) -> Result<u8, Error<SpiE>> {
-        let mut last = 0;
-
-        loop {
+    /// Wait for at least `min` bytes in the RX FIFO, failing with `Error::RxOverflow` as soon as
+    /// RXBYTES reports an overflow, and with `Error::Timeout` after the poll limit.
+    fn rx_bytes_available(&mut self, min: u8) -> Result<u8, Error<SpiE>> {
+        for _ in 0..self.0.poll_limit {
             let num_rxbytes = self.get_rx_bytes()?;
 
-            if (num_rxbytes > 0) && (num_rxbytes == last) {
-                break;
+            if num_rxbytes >= min {
+                return Ok(num_rxbytes);
             }
-
-            last = num_rxbytes;
         }
-        Ok(last)
+        Err(Error::Timeout)
     }
 
     /// Should also be able to configure MCSM1.RXOFF_MODE to declare what state
     /// to enter after fully receiving a packet.
     /// Possible targets: IDLE, FSTON, TX, RX
+    /// Waiting for data longer than the poll limit fails with `Error::Timeout`, the radio stays in
+    /// RX. An RX FIFO overflow is recovered, salvaging the complete packets left in the FIFO.
     pub fn receive(&mut self, addr: &mut u8, buf: &mut [u8]) -> Result<u8, Error<SpiE>> {
-        match self.rx_bytes_available() {
+        self.require(Capability::Receive)?;
+
+        match self.rx_bytes_available(1) {
             Ok(_nbytes) => {
                 let mut length: Option<u8> = Some(0);
                 let mut address: Option<u8> = Some(0);
@@ -732,6 +1300,9 @@ where
                     Ok(length.unwrap())
                 }
             }
+            Err(Error::RxOverflow) => self.recover_rx_overflow(addr, buf, true),
+            // Still waiting in RX, where SFRX is not allowed
+            Err(Error::Timeout) => Err(Error::Timeout),
             Err(err) => {
                 self.flush_rx_fifo_buffer()?;
                 Err(err)
@@ -739,7 +1310,248 @@ where
         }
     }
 
+    /// Salvage a complete packet still in the overflowed RX FIFO. The packet length is read from
+    /// its length byte, or from PKTLEN for fixed length packets, and the packet is only read when
+    /// all of it, including the appended status bytes, is in the FIFO and fits `buf`. Returns the
+    /// packet length. Once no complete packet is left, flush the RX FIFO and return to RX if so
+    /// configured by MCSM1.RXOFF_MODE. The CRC_OK bit of the appended status is checked if `crc`.
+    fn recover_rx_overflow(
+        &mut self,
+        addr: &mut u8,
+        buf: &mut [u8],
+        crc: bool,
+    ) -> Result<u8, Error<SpiE>> {
+        let available = RXBYTES(self.0.read_status_stable(Status::RXBYTES)?).num_rxbytes();
+        let status_len = 2 * self.0.rx_status_fields as usize;
+
+        let (length, remaining) = match self.0.length_field {
+            true if available > 0 => {
+                let mut length = [0];
+                self.0.access_fifo(Access::Read, &mut [], &mut length)?;
+                (Some(length[0]), available - 1)
+            }
+            true => (None, available),
+            false => (Some(self.0.read_register(Config::PKTLEN)?), available),
+        };
+
+        if let Some(length) = length {
+            // The packet length includes the address byte
+            let address_len = self.0.address_field as usize;
+            let packet_len = length as usize + status_len;
+            let data_len = packet_len.saturating_sub(address_len);
+            let complete = length as usize >= address_len && remaining as usize >= packet_len;
+
+            if complete && data_len <= buf.len() {
+                let mut address = [0];
+                let data = &mut buf[..data_len];
+                self.0.access_fifo(Access::Read, &mut address[..address_len], data)?;
+                *addr = address[0];
+                // Leave the FIFO in RXFIFO_OVERFLOW, the next call salvages or flushes the remainder
+                if self.0.rx_status_fields {
+                    let lqi = data[data_len - 1];
+                    // Overwrite the status bytes, as `read_data` does
+                    data[data_len - 2..].fill(0);
+                    if crc && (lqi >> 7) != 1 {
+                        return Err(Error::CrcMismatch);
+                    }
+                }
+                return Ok(length);
+            }
+        }
+
+        // SFRX is allowed in RXFIFO_OVERFLOW, and leaves the radio in IDLE
+        self.flush_rx_fifo_buffer()?;
+        if self.get_rxoff_mode()? == RxOffMode::Rx {
+            self.enable_rx()?;
+        }
+        Err(Error::RxOverflowRecovered {
+            discarded: available,
+        })
+    }
+
+    /// Send a packet, waiting until it has been transmitted.
+    /// The length field is derived from `data`, and the address field is required when address
+    /// filtering is enabled. Packets larger than the TX FIFO are written in chunks while
+    /// transmitting, using the free byte count from the chip status byte.
+    /// A TX FIFO underflow is recovered by flushing the TX FIFO, returning to RX if so configured by
+    /// MCSM1.TXOFF_MODE.
+    /// Transmitting from RX with clear channel assessment enabled fails with `Error::ChannelBusy`
+    /// when the channel is busy, the packet is discarded and the radio returns to RX. Waiting
+    /// longer than the poll limit, see [`Cc1101::set_poll_limit`], fails with `Error::Timeout`.
+    pub fn transmit(&mut self, address: Option<u8>, data: &mut [u8]) -> Result<(), Error<SpiE>> {
+        let num_of_optional_fields = self.0.length_field as usize + self.0.address_field as usize;
+        let packet_len = data.len() + self.0.address_field as usize;
+        if packet_len > u8::MAX as usize {
+            return Err(Error::UserInputError(UserError::ArrayTooLong(data.len())));
+        }
+
+        let first = data.len().min(FIFO_SIZE_MAX as usize - num_of_optional_fields);
+        let (head, mut rest) = data.split_at_mut(first);
+        let mut length = Some(packet_len as u8);
+        let mut address = address;
+        self.write_data(&mut length, &mut address, head)?;
+        self.enable_tx()?;
+
+        let mut free = 0;
+        let mut polls = 0;
+        while !rest.is_empty() {
+            if free == 0 {
+                if polls == self.0.poll_limit {
+                    self.abort_transmission()?;
+                    return Err(Error::Timeout);
+                }
+                polls += 1;
+
+                let status = self.0.read_chip_status(Access::Write)?;
+                match status.state {
+                    State::TXFIFO_UNDERFLOW => {
+                        let txoff_mode = self.get_txoff_mode()?;
+                        return self.recover_tx_underflow(txoff_mode);
+                    }
+                    State::RX => return self.abort_busy_transmission(),
+                    _ => {}
+                }
+                free = status.tx_bytes_free().unwrap_or(0) as usize;
+                continue;
+            }
+
+            polls = 0;
+            let (chunk, tail) = rest.split_at_mut(free.min(rest.len()));
+            self.0.access_fifo(Access::Write, &mut [], chunk)?;
+            // The status byte reports the free bytes before the chunk was written
+            let status = self.0.status.unwrap_or_default();
+            free = (status.tx_bytes_free().unwrap_or(0) as usize).saturating_sub(chunk.len());
+            rest = tail;
+        }
+
+        self.await_transmission()
+    }
+
+    /// Wait until the TX FIFO has been sent. The radio is done once it is back in a state it
+    /// leaves TX for and the TX FIFO is empty, which also covers packets completing between two
+    /// reads. STX is ignored in RX when clear channel assessment reports the channel busy, which
+    /// leaves the radio in RX with the packet still in the TX FIFO.
+    fn await_transmission(&mut self) -> Result<(), Error<SpiE>> {
+        let txoff_mode = self.get_txoff_mode()?;
+
+        for _ in 0..self.0.poll_limit {
+            let state = self.get_machine_state()?;
+            let tx_bytes = match state {
+                MachineState::IDLE | MachineState::FSTXON | MachineState::RX | MachineState::TX => {
+                    self.get_tx_bytes()
+                }
+                MachineState::TXFIFO_UNDERFLOW => Err(Error::TxUnderflow),
+                _ => continue,
+            };
+            match (state, tx_bytes) {
+                (_, Err(Error::TxUnderflow)) => return self.recover_tx_underflow(txoff_mode),
+                (_, Err(e)) => return Err(e),
+                // Staying in TX after the packet, done once the FIFO has been drained
+                (MachineState::TX, Ok(0)) if txoff_mode == TxOffMode::Tx => return Ok(()),
+                (MachineState::TX, _) => {}
+                (MachineState::RX, Ok(n)) if n > 0 => return self.abort_busy_transmission(),
+                (_, Ok(0)) => return Ok(()),
+                _ => {}
+            }
+        }
+
+        self.abort_transmission()?;
+        Err(Error::Timeout)
+    }
+
+    /// Return to IDLE and flush the TX FIFO, SFTX is only allowed in IDLE.
+    fn abort_transmission(&mut self) -> Result<(), Error<SpiE>> {
+        self.exit_rx_tx()?;
+        self.await_machine_state(MachineState::IDLE)?;
+        self.flush_tx_fifo_buffer()
+    }
+
+    /// Discard the packet not sent because the channel is busy, and return to RX.
+    fn abort_busy_transmission(&mut self) -> Result<(), Error<SpiE>> {
+        self.abort_transmission()?;
+        self.enable_rx()?;
+        Err(Error::ChannelBusy)
+    }
+
+    fn recover_tx_underflow(&mut self, txoff_mode: TxOffMode) -> Result<(), Error<SpiE>> {
+        let unsent = TXBYTES(self.0.read_status_stable(Status::TXBYTES)?).num_txbytes();
+
+        // SFTX is allowed in TXFIFO_UNDERFLOW, and leaves the radio in IDLE
+        self.flush_tx_fifo_buffer()?;
+        if txoff_mode == TxOffMode::Rx {
+            self.enable_rx()?;
+        }
+        Err(Error::TxUnderflowRecovered {
+            unsent,
+        })
+    }
+
+    /// Configure fixed length packets of `len` bytes for software packet handling with
+    /// [`codec::Framing`], see [`Cc1101::transmit_framed`] and [`Cc1101::receive_framed`]. The
+    /// CRC, data whitening and address check of the chip are disabled, `len` includes the CRC.
+    pub fn set_software_framing(&mut self, len: u8) -> Result<(), Error<SpiE>> {
+        self.crc_enable(false)?;
+        self.white_data_enable(false)?;
+        self.set_address_filter(AddressFilter::Disabled)?;
+        self.set_packet_length(PacketLength::Fixed(len))
+    }
+
+    /// Send the frame in `buf[..len]` with software packet handling, as [`Cc1101::transmit`]. The
+    /// frame is encoded in place with `framing`, so `buf` must have room for the CRC, and sent as
+    /// a fixed length packet of the encoded length, see [`Cc1101::set_software_framing`].
+    /// On air, a frame starting with a length byte matches a variable length packet with the
+    /// packet handling of the chip.
+    pub fn transmit_framed(
+        &mut self,
+        framing: Framing,
+        buf: &mut [u8],
+        len: usize,
+    ) -> Result<(), Error<SpiE>> {
+        let total = match framing.encode(buf, len) {
+            Ok(total) if total <= u8::MAX as usize => total,
+            _ => return Err(Error::UserInputError(UserError::ArrayTooLong(len))),
+        };
+        self.0.write_register(Config::PKTLEN, total as u8)?;
+        self.transmit(None, &mut buf[..total])
+    }
+
+    /// Receive a frame with software packet handling into `buf`, waiting for a fixed length
+    /// packet of PKTLEN bytes, see [`Cc1101::set_software_framing`]. The frame is decoded in
+    /// place with `framing`, returns its length without the CRC.
+    /// Waiting longer than the poll limit fails with `Error::Timeout`, the radio stays in RX. A
+    /// frame is salvaged from an overflowed RX FIFO as in [`Cc1101::receive`], when `buf` also has
+    /// room for the appended status bytes.
+    pub fn receive_framed(
+        &mut self,
+        framing: Framing,
+        buf: &mut [u8],
+    ) -> Result<usize, Error<SpiE>> {
+        self.require(Capability::Receive)?;
+
+        let len = self.0.read_register(Config::PKTLEN)? as usize;
+        let status_len = 2 * self.0.rx_status_fields as usize;
+        if len > buf.len() || len + status_len > FIFO_SIZE_MAX as usize {
+            return Err(Error::UserInputError(UserError::ArrayTooLong(len)));
+        }
+        match self.rx_bytes_available((len + status_len) as u8) {
+            Ok(_) => {
+                let mut status = [0; 2];
+                self.0.access_fifo(Access::Read, &mut buf[..len], &mut status[..status_len])?;
+            }
+            // Checked by the framing, the CRC of the chip is disabled
+            Err(Error::RxOverflow) => {
+                self.recover_rx_overflow(&mut 0, buf, false)?;
+            }
+            Err(err) => return Err(err),
+        }
+        framing.decode(&mut buf[..len]).map_err(|e| match e {
+            FrameError::CrcMismatch => Error::CrcMismatch,
+            FrameError::BufferTooSmall => Error::UserInputError(UserError::ArrayTooLong(len)),
+        })
+    }
+
     /// Configures raw data to be passed through, without any packet handling.
+    #[deprecated(note = "use `set_asynchronous_serial_mode` instead")]
     pub fn set_raw_mode(&mut self) -> Result<(), Error<SpiE>> {
         // Serial data output.
         self.set_gdo0_config(GdoCfg::SERIAL_DATA_OUT)?;
@@ -748,3 +1560,369 @@ where
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::Framing;
+    use crate::mock::{radio, Chip};
+    use std::vec::Vec;
+
+    #[test]
+    fn test_identify_chip() {
+        let mut radio = radio();
+        radio.0.spi.version = 0x07;
+        assert_eq!(radio.identify_chip(), Ok(ChipVariant::CC110L));
+        assert_eq!(radio.get_chip_variant(), ChipVariant::CC110L);
+        assert!(!radio.get_capabilities().supports(Capability::WakeOnRadio));
+
+        radio.0.spi.partnum = 0x80;
+        assert_eq!(radio.identify_chip(), Err(Error::UnknownChip(0x80, 0x07)));
+        assert_eq!(radio.get_chip_variant(), ChipVariant::CC110L);
+    }
+
+    #[test]
+    fn test_capability_gate() {
+        let mut radio = radio();
+        radio.set_chip_variant(ChipVariant::CC113L);
+        let not_supported = Err(Error::NotSupported(Capability::Transmit));
+        assert_eq!(radio.enable_tx(), not_supported);
+        assert_eq!(radio.write_data(&mut Some(1), &mut None, &mut [0]), not_supported);
+        assert_eq!(radio.transmit(None, &mut [0]), not_supported);
+
+        radio.set_chip_variant(ChipVariant::CC115L);
+        let not_supported = Error::NotSupported(Capability::Receive);
+        assert_eq!(radio.enable_rx(), Err(not_supported));
+        assert_eq!(radio.receive(&mut 0, &mut [0; 3]), Err(not_supported));
+
+        radio.set_chip_variant(ChipVariant::CC110L);
+        let not_supported = Err(Error::NotSupported(Capability::WakeOnRadio));
+        assert_eq!(radio.start_wake_on_radio(), not_supported);
+        assert_eq!(radio.reset_rtc_to_event1(), not_supported);
+
+        // Rejected before any SPI access, register accesses are not checked
+        assert!(radio.0.spi.strobes.is_empty());
+        assert!(radio.0.spi.tx_fifo.is_empty());
+        assert_eq!(radio.set_channel(3), Ok(()));
+        assert_eq!(radio.0.spi.get(Config::CHANNR), 3);
+    }
+
+    #[test]
+    fn test_fifothr_mcsm0_fields() {
+        let mut radio = radio();
+        radio.set_fifo_threshold(FifoThreshold::TX_33_RX_32).unwrap();
+        radio.set_close_in_rx(CloseInRx::Db12).unwrap();
+        radio.adc_retention_enable(true).unwrap();
+        assert_eq!(radio.0.spi.get(Config::FIFOTHR), 0b0110_0111);
+        assert_eq!(radio.get_fifo_threshold(), Ok(FifoThreshold::TX_33_RX_32));
+        assert_eq!(radio.get_close_in_rx(), Ok(CloseInRx::Db12));
+        assert_eq!(radio.is_adc_retention_enabled(), Ok(true));
+
+        radio.set_autocalibration(AutoCalibration::FromIdle).unwrap();
+        radio.set_power_on_timeout(PoTimeout::EXPIRE_COUNT_256).unwrap();
+        radio.pin_ctrl_enable(true).unwrap();
+        radio.xosc_force_on_enable(true).unwrap();
+        assert_eq!(radio.0.spi.get(Config::MCSM0), 0b0001_1111);
+        assert_eq!(radio.get_power_on_timeout(), Ok(PoTimeout::EXPIRE_COUNT_256));
+        assert_eq!(radio.is_pin_ctrl_enabled(), Ok(true));
+        assert_eq!(radio.is_xosc_force_on_enabled(), Ok(true));
+
+        radio.set_preamble_quality_threshold(9).unwrap();
+        assert_eq!(radio.get_preamble_quality_threshold(), Ok(7));
+    }
+
+    #[test]
+    fn test_sync_mode() {
+        let cases = [
+            (SyncMode::Disabled, 0b000, 0xD391),
+            (SyncMode::MatchPartial(0x1234), 0b001, 0x1234),
+            (SyncMode::MatchFull(0x1234), 0b010, 0x1234),
+            (SyncMode::MatchPartialRepeated(0x1234), 0b011, 0x1234),
+            (SyncMode::MatchPartial32(0xABCD_ABCD), 0b011, 0xABCD),
+            (SyncMode::CarrierSense, 0b100, 0xD391),
+            (SyncMode::MatchPartialCarrierSense(0x1234), 0b101, 0x1234),
+            (SyncMode::MatchFullCarrierSense(0x1234), 0b110, 0x1234),
+            (SyncMode::MatchPartialRepeatedCarrierSense(0x1234), 0b111, 0x1234),
+            (SyncMode::MatchPartial32CarrierSense(0xABCD_ABCD), 0b111, 0xABCD),
+        ];
+        for (sync_mode, sync_check, word) in cases {
+            let mut radio = radio();
+            radio.0.spi.config[Config::SYNC1 as usize] = 0;
+            radio.0.spi.config[Config::SYNC0 as usize] = 0;
+            radio.set_sync_mode(sync_mode).unwrap();
+            let mdmcfg2 = radio.0.spi.get(Config::MDMCFG2);
+            assert_eq!(mdmcfg2 & 0b111, sync_check, "{:?}", sync_mode);
+            // Modulation format and Manchester encoding are left untouched
+            assert_eq!(mdmcfg2 & !0b111, MDMCFG2::default().bits() & !0b111, "{:?}", sync_mode);
+            assert_eq!(radio.0.spi.get(Config::SYNC1), (word >> 8) as u8, "{:?}", sync_mode);
+            assert_eq!(radio.0.spi.get(Config::SYNC0), word as u8, "{:?}", sync_mode);
+        }
+    }
+
+    #[test]
+    fn test_sync_mode_32_bits() {
+        let mut radio = radio();
+        let cases = [
+            (SyncMode::MatchPartial32(0xABCD_1234), 0xABCD_1234),
+            (SyncMode::MatchPartial32CarrierSense(0x0000_FFFF), 0x0000_FFFF),
+        ];
+        for (sync_mode, word) in cases {
+            let invalid = Error::UserInputError(UserError::InvalidSyncWord(word));
+            assert_eq!(radio.set_sync_mode(sync_mode), Err(invalid));
+        }
+        // Rejected before any register is written
+        assert_eq!(radio.0.spi.get(Config::MDMCFG2), MDMCFG2::default().bits());
+    }
+
+    #[test]
+    fn test_receive_timeout() {
+        let mut radio = radio();
+        radio.set_poll_limit(10);
+        radio.0.spi.state = MachineState::RX;
+        assert_eq!(radio.receive(&mut 0, &mut [0; 4]), Err(Error::Timeout));
+        assert_eq!(radio.0.spi.strobes, []);
+        assert_eq!(radio.0.spi.state, MachineState::RX);
+    }
+
+    #[test]
+    fn test_rx_overflow_salvage() {
+        let mut radio = radio();
+        radio.set_packet_length(PacketLength::Variable(61)).unwrap();
+        radio.0.spi.state = MachineState::RX;
+        radio.0.spi.receive(&[3, 1, 2, 3, 0x40, 0x80]);
+        radio.0.spi.receive(&[60; 60]);
+
+        // Short packet in a large buffer
+        let mut buf = [0xFF; 10];
+        assert_eq!(radio.receive(&mut 0, &mut buf), Ok(3));
+        assert_eq!(buf, [1, 2, 3, 0, 0, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF]);
+        assert_eq!(radio.0.spi.rx_fifo.len(), 58);
+
+        // Incomplete packet
+        let discarded = Error::RxOverflowRecovered {
+            discarded: 58,
+        };
+        assert_eq!(radio.receive(&mut 0, &mut buf), Err(discarded));
+        let chip = &radio.0.spi;
+        assert_eq!(
+            (chip.state, chip.rx_overflow, chip.rx_fifo.len()),
+            (MachineState::IDLE, false, 0)
+        );
+        assert_eq!(chip.illegal, []);
+    }
+
+    #[test]
+    fn test_rx_overflow_long_packet() {
+        let mut radio = radio();
+        radio.set_packet_length(PacketLength::Variable(61)).unwrap();
+        radio.set_rxoff_mode(RxOffMode::Rx).unwrap();
+        radio.0.spi.state = MachineState::RX;
+        radio.0.spi.receive(&[10; 13]);
+        radio.0.spi.receive(&[60; 60]);
+
+        let discarded = Error::RxOverflowRecovered {
+            discarded: 64,
+        };
+        assert_eq!(radio.receive(&mut 0, &mut [0; 4]), Err(discarded));
+        let chip = &radio.0.spi;
+        assert_eq!(chip.strobes, [Command::SFRX, Command::SRX]);
+        assert_eq!(chip.state, MachineState::RX);
+    }
+
+    #[test]
+    fn test_rx_overflow_fixed_length() {
+        let mut radio = radio();
+        radio.set_packet_length(PacketLength::Fixed(3)).unwrap();
+        radio.set_address_filter(AddressFilter::Device(7)).unwrap();
+        radio.0.spi.state = MachineState::RX;
+        radio.0.spi.receive(&[7, 1, 2, 0x40, 0x00]);
+        for _ in 0..12 {
+            radio.0.spi.receive(&[7, 3, 4, 0x40, 0x80]);
+        }
+
+        let (mut addr, mut buf) = (0, [0xFF; 4]);
+        assert_eq!(radio.receive(&mut addr, &mut buf), Err(Error::CrcMismatch));
+        assert_eq!(radio.receive(&mut addr, &mut buf), Ok(3));
+        assert_eq!((addr, buf), (7, [3, 4, 0, 0]));
+        assert_eq!(radio.0.spi.rx_fifo.len(), 54);
+    }
+
+    #[test]
+    fn test_fifo_flags() {
+        let mut radio = radio();
+        radio.0.spi.tx_fifo.extend([1, 2, 3]);
+        assert_eq!(radio.get_tx_bytes(), Ok(3));
+        radio.0.spi.tx_underflow = true;
+        assert_eq!(radio.get_tx_bytes(), Err(Error::TxUnderflow));
+
+        radio.0.spi.rx_fifo.extend([1, 2]);
+        assert_eq!(radio.get_rx_bytes(), Ok(2));
+        radio.0.spi.rx_overflow = true;
+        assert_eq!(radio.get_rx_bytes(), Err(Error::RxOverflow));
+    }
+
+    #[test]
+    fn test_rx_bytes_available() {
+        let mut radio = radio();
+        // Counting up while the first bytes arrive, then stable
+        radio.0.spi.queue_status(Status::RXBYTES, &[0, 0, 0, 1, 2, 2]);
+        assert_eq!(radio.rx_bytes_available(1), Ok(2));
+
+        // Overflow flag set while waiting
+        radio.0.spi.queue_status(Status::RXBYTES, &[0, 0, 0x80, 0x80]);
+        assert_eq!(radio.rx_bytes_available(1), Err(Error::RxOverflow));
+
+        radio.set_poll_limit(3);
+        assert_eq!(radio.rx_bytes_available(1), Err(Error::Timeout));
+
+        // Waiting for a minimum
+        radio.0.spi.receive(&[1, 2, 3]);
+        assert_eq!(radio.rx_bytes_available(4), Err(Error::Timeout));
+        assert_eq!(radio.rx_bytes_available(3), Ok(3));
+    }
+
+    #[test]
+    fn test_status_observer() {
+        let mut observed = Vec::new();
+        let mut radio =
+            Cc1101::with_status_observer(Chip::new(), |status| observed.push(status)).unwrap();
+        radio.set_channel(3).unwrap();
+        radio.enable_rx().unwrap();
+        radio.0.spi.receive(&[1, 2, 3]);
+        assert_eq!(radio.get_rx_bytes(), Ok(3));
+        radio.exit_rx_tx().unwrap();
+        radio.flush_rx_fifo_buffer().unwrap();
+        let returned = core::mem::take(&mut radio.0.spi.status_bytes);
+        drop(radio);
+
+        assert_eq!(observed.len(), returned.len());
+        for (status, byte) in observed.iter().zip(returned) {
+            let expected = StatusByte::new(byte, status.access);
+            assert_eq!(status.chip_rdy, expected.chip_rdy);
+            assert_eq!(status.state, expected.state);
+            assert_eq!(status.fifo_bytes_available, expected.fifo_bytes_available);
+        }
+        // Read accesses report the RX FIFO
+        assert!(observed.iter().any(|status| status.rx_bytes_available() == Some(3)));
+    }
+
+    #[test]
+    fn test_sleep_wake() {
+        let mut radio = radio();
+        let patable = [0x12, 0x0E, 0x1D, 0x34, 0x60, 0x84, 0xC8, 0xC0];
+        radio.0.spi.patable = patable;
+        radio.0.spi.config[Config::TEST2 as usize..=Config::TEST0 as usize]
+            .copy_from_slice(&[0x81, 0x35, 0x09]);
+        radio.set_channel(7).unwrap();
+
+        radio.sleep().unwrap();
+        let chip = &radio.0.spi;
+        assert_eq!(chip.state, MachineState::SLEEP);
+        assert_eq!((chip.patable, chip.get(Config::TEST2)), ([0; PATABLE_SIZE], 0));
+
+        radio.0.spi.wake_transactions = 3;
+        radio.wake(false).unwrap();
+        let chip = &radio.0.spi;
+        assert_eq!(chip.state, MachineState::IDLE);
+        assert_eq!(chip.patable, patable);
+        assert_eq!(
+            chip.config[Config::TEST2 as usize..=Config::TEST0 as usize],
+            [0x81, 0x35, 0x09]
+        );
+        assert!(!chip.strobes.contains(&Command::SCAL));
+
+        // Calibrated on request, and nothing left to restore
+        radio.0.spi.patable = [0; PATABLE_SIZE];
+        radio.0.spi.wake_transactions = 1;
+        radio.enter_power_down_mode().unwrap();
+        radio.wake(true).unwrap();
+        let chip = &radio.0.spi;
+        assert_eq!(chip.strobes.last(), Some(&Command::SCAL));
+        assert_eq!(chip.get(Config::FSCAL1), 7);
+        assert_eq!(chip.patable, [0; PATABLE_SIZE]);
+    }
+
+    #[test]
+    fn test_wake_not_ready() {
+        let mut radio = radio();
+        radio.sleep().unwrap();
+        radio.0.spi.wake_transactions = WAKE_POLLS as u32 + 1;
+        assert_eq!(radio.wake(false), Err(Error::ChipNotReady));
+
+        // Nothing restored before the chip is ready
+        let chip = &radio.0.spi;
+        assert_eq!(
+            chip.status_bytes[chip.status_bytes.len() - WAKE_POLLS as usize..],
+            [0x80; WAKE_POLLS as usize]
+        );
+        assert_eq!(chip.patable, [0; PATABLE_SIZE]);
+        assert!(radio.0.sleep_context.is_some());
+    }
+
+    fn framed(framing: Framing) -> ([u8; 6], [u8; 8]) {
+        let mut frame = [0x03, 0x01, 0x02, 0x03, 0, 0];
+        assert_eq!(framing.encode(&mut frame, 4), Ok(6));
+        let mut received = [0; 8];
+        received[..6].copy_from_slice(&frame);
+        // Appended status, CRC_OK low as the CRC of the chip is disabled
+        received[6..].copy_from_slice(&[0x40, 0x00]);
+        (frame, received)
+    }
+
+    #[test]
+    fn test_receive_framed() {
+        let framing = Framing {
+            crc: true,
+            whitening: true,
+        };
+        let (_, received) = framed(framing);
+        let mut radio = radio();
+        radio.set_software_framing(6).unwrap();
+        radio.set_poll_limit(10);
+        radio.0.spi.state = MachineState::RX;
+
+        // Waits for the whole packet, including the status bytes
+        radio.0.spi.receive(&received[..7]);
+        let mut buf = [0; 6];
+        assert_eq!(radio.receive_framed(framing, &mut buf), Err(Error::Timeout));
+        assert_eq!(radio.0.spi.rx_fifo.len(), 7);
+
+        radio.0.spi.receive(&received[7..]);
+        assert_eq!(radio.receive_framed(framing, &mut buf), Ok(4));
+        assert_eq!(buf[..4], [0x03, 0x01, 0x02, 0x03]);
+        assert!(radio.0.spi.rx_fifo.is_empty());
+    }
+
+    #[test]
+    fn test_receive_framed_overflow() {
+        let framing = Framing {
+            crc: true,
+            whitening: false,
+        };
+        let (frame, received) = framed(framing);
+        let mut radio = radio();
+        radio.set_software_framing(6).unwrap();
+        radio.0.spi.state = MachineState::RX;
+        radio.0.spi.receive(&received);
+        radio.0.spi.receive(&[0x55; 60]);
+
+        // Salvaged, with room for the status bytes
+        let mut buf = [0xFF; 8];
+        assert_eq!(radio.receive_framed(framing, &mut buf), Ok(4));
+        assert_eq!(buf[..6], frame);
+        assert_eq!(buf[6..], [0, 0]);
+
+        // Fixed length, the next bytes are taken for a frame
+        assert_eq!(radio.receive_framed(framing, &mut buf), Err(Error::CrcMismatch));
+
+        // Incomplete frame left
+        radio.0.spi.rx_fifo.truncate(3);
+        let discarded = Error::RxOverflowRecovered {
+            discarded: 3,
+        };
+        assert_eq!(radio.receive_framed(framing, &mut buf), Err(discarded));
+        let chip = &radio.0.spi;
+        assert_eq!((chip.rx_overflow, chip.rx_fifo.len()), (false, 0));
+        assert_eq!(chip.illegal, []);
+    }
+}
@@ -0,0 +1,525 @@
+//! Reliable link layer with acknowledgements, retransmission and duplicate suppression.
+//!
+//! Frames are variable length packets addressed with the hardware address byte, followed by a
+//! header holding the source address, flags and a sequence number. A [`Link`] sends a frame and
+//! waits for the acknowledgement of its sequence number, retrying with exponential backoff. On
+//! the receive side, every frame requesting it is acknowledged, and frames repeating the last
+//! sequence number seen from a source are acknowledged again but not delivered.
+//!
+//! The radio is configured with MCSM1.RXOFF_MODE = FSTXON, so an acknowledgement goes out
+//! without waiting for the frequency synthesizer to settle, and MCSM1.TXOFF_MODE = RX, so the
+//! sender listens for the acknowledgement right after its frame.
+//!
+//! Frames fit the FIFO in one go, limiting the payload to [`MAX_PAYLOAD`] bytes.
+
+use crate::lowlevel::{access::Access, convert::from_rssi_to_rssi_dbm};
+use crate::{
+    AddressFilter, Cc1101, Error, MachineState, PacketLength, RxOffMode, StatusByte, TxOffMode,
+    UserError, FIFO_SIZE_MAX,
+};
+use hal::{delay::DelayNs, spi::SpiDevice};
+
+/// Destination address of frames to all nodes, never acknowledged.
+pub const BROADCAST: u8 = 0x00;
+/// Header after the address byte: source address, flags and sequence number.
+const HEADER_SIZE: usize = 3;
+/// Address byte, header and payload, leaving room for the length byte and the appended status.
+const MAX_PACKET: usize = FIFO_SIZE_MAX as usize - 3;
+/// Largest payload of a frame.
+pub const MAX_PAYLOAD: usize = MAX_PACKET - 1 - HEADER_SIZE;
+/// Interval at which the radio is polled while waiting for a frame.
+const POLL_US: u32 = 100;
+
+const FLAG_ACK: u8 = 0x01;
+const FLAG_ACK_REQUEST: u8 = 0x02;
+
+/// Retransmission settings.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct LinkConfig {
+    /// Retransmissions after the first attempt.
+    pub retries: u8,
+    /// Time to wait for an acknowledgement, in microseconds.
+    pub ack_timeout_us: u32,
+    /// Backoff before the first retransmission, in microseconds. It doubles with each retry, up to
+    /// 16 times this value, and a random delay of up to the same amount is added.
+    pub backoff_us: u32,
+}
+
+impl LinkConfig {
+    /// Backoff before the given retransmission, without jitter, saturating at `u32::MAX`.
+    fn backoff(&self, attempt: u8) -> u32 {
+        let factor = 1 << attempt.saturating_sub(1).min(4);
+        self.backoff_us.saturating_mul(factor)
+    }
+}
+
+impl Default for LinkConfig {
+    fn default() -> Self {
+        LinkConfig {
+            retries: 3,
+            ack_timeout_us: 20_000,
+            backoff_us: 5_000,
+        }
+    }
+}
+
+/// Link layer errors.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum LinkError<SpiE> {
+    /// Radio error.
+    Radio(Error<SpiE>),
+    /// No acknowledgement received after all retries.
+    NoAck,
+    /// Channel busy on the last retry, with clear channel assessment enabled.
+    ChannelBusy,
+}
+
+impl<SpiE> From<Error<SpiE>> for LinkError<SpiE> {
+    fn from(e: Error<SpiE>) -> Self {
+        LinkError::Radio(e)
+    }
+}
+
+/// Frame delivered by [`Link::receive`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Received {
+    /// Source address.
+    pub source: u8,
+    /// Destination address, [`BROADCAST`] or the own address.
+    pub destination: u8,
+    /// Payload length.
+    pub len: usize,
+    /// Received signal strength, in dBm.
+    pub rssi_dbm: i16,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+struct Header {
+    destination: u8,
+    source: u8,
+    flags: u8,
+    seq: u8,
+}
+
+impl Header {
+    fn parse(packet: &[u8]) -> Option<Self> {
+        match *packet {
+            [destination, source, flags, seq, ..] => Some(Header {
+                destination,
+                source,
+                flags,
+                seq,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Last sequence number seen from each of up to `P` sources, replacing the oldest entry.
+struct DuplicateFilter<const P: usize> {
+    entries: [Option<(u8, u8)>; P],
+    next: usize,
+}
+
+impl<const P: usize> DuplicateFilter<P> {
+    fn new() -> Self {
+        DuplicateFilter {
+            entries: [None; P],
+            next: 0,
+        }
+    }
+
+    /// Record `seq` from `source`, returns whether it repeats the last one.
+    fn check(&mut self, source: u8, seq: u8) -> bool {
+        for entry in self.entries.iter_mut().flatten() {
+            if entry.0 == source {
+                let duplicate = entry.1 == seq;
+                entry.1 = seq;
+                return duplicate;
+            }
+        }
+        if P > 0 {
+            self.entries[self.next] = Some((source, seq));
+            self.next = (self.next + 1) % P;
+        }
+        false
+    }
+}
+
+/// Link layer endpoint with its own address, tracking duplicates from up to `P` peers.
+pub struct Link<D, const P: usize> {
+    address: u8,
+    config: LinkConfig,
+    delay: D,
+    seq: u8,
+    duplicates: DuplicateFilter<P>,
+    random: u32,
+}
+
+impl<D: DelayNs, const P: usize> Link<D, P> {
+    /// Configure the radio for the link layer with node address `address`: variable packet
+    /// length, address filtering with broadcast, appended status bytes and the RX/TX turnaround.
+    /// CRC should be enabled.
+    pub fn new<SPI, SpiE, O>(
+        radio: &mut Cc1101<SPI, O>,
+        address: u8,
+        config: LinkConfig,
+        delay: D,
+    ) -> Result<Self, Error<SpiE>>
+    where
+        SPI: SpiDevice<u8, Error = SpiE>,
+        O: FnMut(StatusByte),
+    {
+        if address == BROADCAST {
+            return Err(Error::UserInputError(UserError::InvalidAddress(address)));
+        }
+
+        radio.set_packet_length(PacketLength::Variable(MAX_PACKET as u8))?;
+        radio.set_address_filter(AddressFilter::DeviceLowBroadcast(address))?;
+        radio.append_status_enable(true)?;
+        radio.set_rxoff_mode(RxOffMode::FsTxOn)?;
+        radio.set_txoff_mode(TxOffMode::Rx)?;
+
+        Ok(Link {
+            address,
+            config,
+            delay,
+            seq: 0,
+            duplicates: DuplicateFilter::new(),
+            random: 0x9E37_79B9 ^ address as u32,
+        })
+    }
+
+    /// Own address.
+    pub fn address(&self) -> u8 {
+        self.address
+    }
+
+    /// Release the delay provider.
+    pub fn release(self) -> D {
+        self.delay
+    }
+
+    /// Send `payload` to `destination`, waiting for the acknowledgement unless it is
+    /// [`BROADCAST`]. Frames from other nodes received while waiting are dropped, their senders
+    /// retransmit them. A frame not sent because the channel is busy is retried like a missing
+    /// acknowledgement. The radio is left in FSTXON after an acknowledgement, otherwise in RX.
+    pub fn send<SPI, SpiE, O>(
+        &mut self,
+        radio: &mut Cc1101<SPI, O>,
+        destination: u8,
+        payload: &[u8],
+    ) -> Result<(), LinkError<SpiE>>
+    where
+        SPI: SpiDevice<u8, Error = SpiE>,
+        O: FnMut(StatusByte),
+    {
+        if payload.len() > MAX_PAYLOAD {
+            return Err(Error::UserInputError(UserError::ArrayTooLong(payload.len())).into());
+        }
+        self.seq = self.seq.wrapping_add(1);
+        let broadcast = destination == BROADCAST;
+        let flags = if broadcast {
+            0
+        } else {
+            FLAG_ACK_REQUEST
+        };
+
+        let mut packet = [0; MAX_PACKET];
+        let mut busy = false;
+        for attempt in 0..=self.config.retries {
+            if attempt > 0 {
+                let jitter_us = self.next_random() % self.config.backoff_us.max(1);
+                self.delay.delay_us(self.config.backoff(attempt).saturating_add(jitter_us));
+            }
+
+            match self.send_frame(radio, destination, flags, self.seq, payload) {
+                Ok(()) => busy = false,
+                // Not sent, the radio is back in RX
+                Err(Error::ChannelBusy) => {
+                    busy = true;
+                    continue;
+                }
+                Err(e) => return Err(e.into()),
+            }
+            if broadcast {
+                return Ok(());
+            }
+
+            let mut waited_us = 0;
+            while let Some((header, _, _)) =
+                self.receive_packet(radio, &mut packet, &mut waited_us, self.config.ack_timeout_us)?
+            {
+                if header.flags & FLAG_ACK != 0
+                    && header.source == destination
+                    && header.seq == self.seq
+                {
+                    return Ok(());
+                }
+            }
+        }
+        match busy {
+            true => Err(LinkError::ChannelBusy),
+            false => Err(LinkError::NoAck),
+        }
+    }
+
+    /// Wait up to `timeout_us` for a frame and copy its payload into `buf`, which is truncated if
+    /// too short. Acknowledgements are sent as requested. Duplicates and stray acknowledgements
+    /// are dropped. The radio is left in RX, or in FSTXON after a delivered frame that was not
+    /// acknowledged.
+    pub fn receive<SPI, SpiE, O>(
+        &mut self,
+        radio: &mut Cc1101<SPI, O>,
+        buf: &mut [u8],
+        timeout_us: u32,
+    ) -> Result<Option<Received>, LinkError<SpiE>>
+    where
+        SPI: SpiDevice<u8, Error = SpiE>,
+        O: FnMut(StatusByte),
+    {
+        let mut packet = [0; MAX_PACKET];
+        let mut waited_us = 0;
+
+        while let Some((header, len, rssi_dbm)) =
+            self.receive_packet(radio, &mut packet, &mut waited_us, timeout_us)?
+        {
+            if header.flags & FLAG_ACK != 0 {
+                continue;
+            }
+
+            if header.flags & FLAG_ACK_REQUEST != 0 && header.destination == self.address {
+                // Straight from FSTXON, the synthesizer is already settled
+                self.send_frame(radio, header.source, FLAG_ACK, header.seq, &[])?;
+            }
+            if self.duplicates.check(header.source, header.seq) {
+                continue;
+            }
+
+            let payload = &packet[1 + HEADER_SIZE..len];
+            let n = payload.len().min(buf.len());
+            buf[..n].copy_from_slice(&payload[..n]);
+            return Ok(Some(Received {
+                source: header.source,
+                destination: header.destination,
+                len: n,
+                rssi_dbm,
+            }));
+        }
+        Ok(None)
+    }
+
+    fn send_frame<SPI, SpiE, O>(
+        &mut self,
+        radio: &mut Cc1101<SPI, O>,
+        destination: u8,
+        flags: u8,
+        seq: u8,
+        payload: &[u8],
+    ) -> Result<(), Error<SpiE>>
+    where
+        SPI: SpiDevice<u8, Error = SpiE>,
+        O: FnMut(StatusByte),
+    {
+        let mut frame = [0; MAX_PACKET - 1];
+        frame[..HEADER_SIZE].copy_from_slice(&[self.address, flags, seq]);
+        frame[HEADER_SIZE..HEADER_SIZE + payload.len()].copy_from_slice(payload);
+        radio.transmit(Some(destination), &mut frame[..HEADER_SIZE + payload.len()])
+    }
+
+    /// Wait until `waited_us` reaches `timeout_us` for a packet with a valid CRC and header, read
+    /// into `packet` starting with the address byte. Returns the header, the packet length and the
+    /// RSSI.
+    fn receive_packet<SPI, SpiE, O>(
+        &mut self,
+        radio: &mut Cc1101<SPI, O>,
+        packet: &mut [u8; MAX_PACKET],
+        waited_us: &mut u32,
+        timeout_us: u32,
+    ) -> Result<Option<(Header, usize, i16)>, Error<SpiE>>
+    where
+        SPI: SpiDevice<u8, Error = SpiE>,
+        O: FnMut(StatusByte),
+    {
+        loop {
+            match radio.get_machine_state()? {
+                MachineState::RXFIFO_OVERFLOW => {
+                    radio.flush_rx_fifo_buffer()?;
+                    radio.enable_rx()?;
+                }
+                // RXOFF_MODE went to FSTXON after a packet
+                MachineState::IDLE | MachineState::FSTXON => match radio.get_rx_bytes() {
+                    Ok(0) => radio.enable_rx()?,
+                    Ok(_) => {
+                        if let Some(received) = Self::read_packet(radio, packet)? {
+                            // Count each packet as a poll, so constant traffic cannot stall
+                            *waited_us = waited_us.saturating_add(POLL_US);
+                            return Ok(Some(received));
+                        }
+                        radio.enable_rx()?;
+                    }
+                    Err(Error::RxOverflow) => {
+                        radio.flush_rx_fifo_buffer()?;
+                        radio.enable_rx()?;
+                    }
+                    Err(e) => return Err(e),
+                },
+                _ => {}
+            }
+
+            if *waited_us >= timeout_us {
+                return Ok(None);
+            }
+            self.delay.delay_us(POLL_US);
+            *waited_us = waited_us.saturating_add(POLL_US);
+        }
+    }
+
+    fn read_packet<SPI, SpiE, O>(
+        radio: &mut Cc1101<SPI, O>,
+        packet: &mut [u8; MAX_PACKET],
+    ) -> Result<Option<(Header, usize, i16)>, Error<SpiE>>
+    where
+        SPI: SpiDevice<u8, Error = SpiE>,
+        O: FnMut(StatusByte),
+    {
+        let mut length = [0];
+        radio.0.access_fifo(Access::Read, &mut [], &mut length)?;
+        let len = length[0] as usize;
+        if len == 0 || len > MAX_PACKET {
+            // SFRX is only allowed in IDLE
+            radio.exit_rx_tx()?;
+            radio.await_machine_state(MachineState::IDLE)?;
+            radio.flush_rx_fifo_buffer()?;
+            return Ok(None);
+        }
+
+        // The whole packet is in the FIFO, reading it leaves the FIFO empty
+        let mut status = [0; 2];
+        radio.0.access_fifo(Access::Read, &mut packet[..len], &mut status)?;
+
+        let crc_ok = status[1] & 0x80 != 0;
+        match Header::parse(&packet[..len]) {
+            Some(header) if crc_ok => Ok(Some((header, len, from_rssi_to_rssi_dbm(status[0])))),
+            _ => Ok(None),
+        }
+    }
+
+    /// Pseudo-random number for the backoff jitter, xorshift32.
+    fn next_random(&mut self) -> u32 {
+        self.random ^= self.random << 13;
+        self.random ^= self.random >> 17;
+        self.random ^= self.random << 5;
+        self.random
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::radio;
+    use std::vec::Vec;
+
+    /// Records the delays, in microseconds.
+    #[derive(Default)]
+    struct Delay(Vec<u32>);
+
+    impl DelayNs for Delay {
+        fn delay_ns(&mut self, ns: u32) {
+            self.0.push(ns / 1000);
+        }
+
+        fn delay_us(&mut self, us: u32) {
+            self.0.push(us);
+        }
+    }
+
+    #[test]
+    fn test_header() {
+        let header = Header::parse(&[0x10, 0x20, FLAG_ACK_REQUEST, 7, 0xAA]).unwrap();
+        assert_eq!(
+            header,
+            Header {
+                destination: 0x10,
+                source: 0x20,
+                flags: FLAG_ACK_REQUEST,
+                seq: 7,
+            }
+        );
+        assert_eq!(Header::parse(&[0x10, 0x20, 0]), None);
+    }
+
+    #[test]
+    fn test_backoff() {
+        let config = LinkConfig::default();
+        assert_eq!(config.backoff(1), 5_000);
+        assert_eq!(config.backoff(2), 10_000);
+        assert_eq!(config.backoff(5), 80_000);
+        assert_eq!(config.backoff(u8::MAX), 80_000);
+
+        let config = LinkConfig {
+            backoff_us: u32::MAX / 2,
+            ..LinkConfig::default()
+        };
+        assert_eq!(config.backoff(3), u32::MAX);
+    }
+
+    #[test]
+    fn test_duplicates() {
+        let mut filter = DuplicateFilter::<2>::new();
+        assert!(!filter.check(1, 5));
+        assert!(filter.check(1, 5));
+        assert!(!filter.check(1, 6));
+        assert!(!filter.check(2, 6));
+
+        // Source 3 replaces the oldest entry, source 1
+        assert!(!filter.check(3, 1));
+        assert!(!filter.check(1, 6));
+        assert!(filter.check(1, 6));
+    }
+
+    #[test]
+    fn test_send_retries_busy_channel() {
+        let mut radio = radio();
+        let mut link =
+            Link::<_, 2>::new(&mut radio, 0x10, LinkConfig::default(), Delay::default()).unwrap();
+        radio.enable_rx().unwrap();
+        radio.0.spi.busy = 2;
+
+        assert_eq!(link.send(&mut radio, BROADCAST, &[0xAA]), Ok(()));
+        let chip = &radio.0.spi;
+        assert_eq!(chip.sent, [[5, BROADCAST, 0x10, 0, 1, 0xAA]]);
+        assert_eq!(chip.illegal, []);
+
+        // Backoff of 5 ms then 10 ms, with jitter of up to 5 ms
+        let delays = link.release().0;
+        assert_eq!(delays.len(), 2);
+        assert!((5_000..10_000).contains(&delays[0]));
+        assert!((10_000..15_000).contains(&delays[1]));
+    }
+
+    #[test]
+    fn test_send_busy_channel() {
+        let mut radio = radio();
+        let config = LinkConfig {
+            ack_timeout_us: 1_000,
+            ..LinkConfig::default()
+        };
+        let mut link = Link::<_, 2>::new(&mut radio, 0x10, config, Delay::default()).unwrap();
+        radio.enable_rx().unwrap();
+
+        // Busy on every attempt
+        radio.0.spi.busy = 4;
+        assert_eq!(link.send(&mut radio, BROADCAST, &[0xAA]), Err(LinkError::ChannelBusy));
+        assert_eq!(radio.0.spi.sent, [] as [Vec<u8>; 0]);
+        assert_eq!(radio.0.spi.state, MachineState::RX);
+
+        // Busy on the first attempt only, then not acknowledged
+        radio.0.spi.busy = 1;
+        assert_eq!(link.send(&mut radio, 0x20, &[0xAA]), Err(LinkError::NoAck));
+        assert_eq!(radio.0.spi.sent.len(), 3);
+    }
+}
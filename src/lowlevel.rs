@@ -9,55 +9,115 @@ mod traits;
 pub mod access;
 pub mod convert;
 pub mod registers;
+pub mod shadow;
 pub mod types;
 
 use self::registers::*;
+use self::shadow::Shadow;
+use self::types::{ChipVariant, SleepContext};
+use crate::Error;
 
 pub const FXOSC: u64 = 26_000_000;
 pub const FIFO_SIZE_MAX: u8 = 64;
+pub const PATABLE_SIZE: usize = 8;
 const BLANK_BYTE: u8 = 0;
+const STATUS_RETRIES: u8 = 8;
+const POLL_LIMIT: u32 = 100_000;
 
-pub struct Cc1101<SPI> {
+pub struct Cc1101<SPI, O = fn(StatusByte)> {
     pub(crate) spi: SPI,
     pub status: Option<StatusByte>,
+    pub status_observer: Option<O>,
     pub length_field: bool,
     pub address_field: bool,
     pub rx_status_fields: bool,
+    pub variant: ChipVariant,
+    pub verify: bool,
+    pub status_retries: u8,
+    pub poll_limit: u32,
+    pub shadow: Option<Shadow>,
+    pub sleep_context: Option<SleepContext>,
 }
 
 impl<SPI, SpiE> Cc1101<SPI>
 where
     SPI: SpiDevice<u8, Error = SpiE>,
 {
-    pub fn new(spi: SPI) -> Result<Self, SpiE> {
+    pub fn new(spi: SPI) -> Result<Self, Error<SpiE>> {
+        Self::with_status_observer(spi, None)
+    }
+}
+
+impl<SPI, SpiE, O> Cc1101<SPI, O>
+where
+    SPI: SpiDevice<u8, Error = SpiE>,
+    O: FnMut(StatusByte),
+{
+    pub fn with_status_observer(spi: SPI, observer: Option<O>) -> Result<Self, Error<SpiE>> {
         let cc1101 = Cc1101 {
             spi,
             status: None,
+            status_observer: observer,
             length_field: false,
             address_field: false,
             rx_status_fields: true,
+            variant: ChipVariant::CC1101,
+            verify: false,
+            status_retries: STATUS_RETRIES,
+            poll_limit: POLL_LIMIT,
+            shadow: None,
+            sleep_context: None,
         };
         Ok(cc1101)
     }
 
-    pub fn read_register<R>(&mut self, reg: R) -> Result<u8, SpiE>
+    /// Read a register, configuration registers are served from the shadow copy when enabled,
+    /// except the ones updated by the chip.
+    pub fn read_register<R>(&mut self, reg: R) -> Result<u8, Error<SpiE>>
     where
         R: Into<Register>,
     {
-        let mut buffer = [reg.into().raddr(access::Mode::Single), BLANK_BYTE];
+        let reg = reg.into();
+        if let (Register::Config(config), Some(shadow)) = (reg, &self.shadow) {
+            if !config.updated_by_chip() {
+                return Ok(shadow.get(config));
+            }
+        }
+        self.read_register_spi(reg)
+    }
+
+    fn read_register_spi(&mut self, reg: Register) -> Result<u8, Error<SpiE>> {
+        let mut buffer = [reg.raddr(access::Mode::Single), BLANK_BYTE];
 
         self.spi.transfer_in_place(&mut buffer)?;
+        spi_trace!("read {} = {=u8:#04x}", reg, buffer[1]);
 
-        self.status = Some(StatusByte::from(buffer[0]));
+        self.update_status(buffer[0], access::Access::Read);
         Ok(buffer[1])
     }
 
+    /// Read a status register until two consecutive values match, as required by the CC1101 errata
+    /// for registers that may change during the SPI read, e.g. RXBYTES, TXBYTES and MARCSTATE.
+    /// At most `status_retries` reads are made after the first one.
+    pub fn read_status_stable(&mut self, reg: Status) -> Result<u8, Error<SpiE>> {
+        let mut last = self.read_register(reg)?;
+
+        for _ in 0..self.status_retries {
+            let value = self.read_register(reg)?;
+            if value == last {
+                return Ok(value);
+            }
+            last = value;
+        }
+        Err(Error::UnstableStatus(reg))
+    }
+
     pub fn access_fifo(
         &mut self,
         access: access::Access,
         optional_fields: &mut [u8],
         data: &mut [u8],
-    ) -> Result<(), SpiE> {
+    ) -> Result<(), Error<SpiE>> {
         let mut buffer = [MultiByte::FIFO.addr(access, access::Mode::Burst)];
 
         if optional_fields.is_empty() {
@@ -72,40 +132,196 @@ where
                 Operation::TransferInPlace(data),
             ])?;
         }
+        spi_trace!("{} FIFO {=[u8]:02x} {=[u8]:02x}", access, optional_fields, data);
 
-        self.status = Some(StatusByte::from(buffer[0]));
+        self.update_status(buffer[0], access);
         Ok(())
     }
 
-    pub fn write_cmd_strobe(&mut self, cmd: Command) -> Result<(), SpiE> {
+    /// Read consecutive configuration registers, starting at `start`, using burst access.
+    /// Dirty registers of the shadow copy are written first.
+    pub fn read_burst(&mut self, start: Config, data: &mut [u8]) -> Result<(), Error<SpiE>> {
+        self.flush_shadow()?;
+
+        let mut buffer = [start.addr(access::Access::Read, access::Mode::Burst)];
+        data.fill(BLANK_BYTE);
+
+        self.spi.transaction(&mut [
+            Operation::TransferInPlace(&mut buffer),
+            Operation::TransferInPlace(data),
+        ])?;
+        spi_trace!("read burst {} = {=[u8]:02x}", start, data);
+
+        self.update_status(buffer[0], access::Access::Read);
+        Ok(())
+    }
+
+    /// Write consecutive configuration registers, starting at `start`, using burst access.
+    pub fn write_burst(&mut self, start: Config, data: &[u8]) -> Result<(), Error<SpiE>> {
+        let mut buffer = [start.addr(access::Access::Write, access::Mode::Burst)];
+
+        self.spi
+            .transaction(&mut [Operation::TransferInPlace(&mut buffer), Operation::Write(data)])?;
+        spi_trace!("write burst {} = {=[u8]:02x}", start, data);
+
+        self.update_status(buffer[0], access::Access::Write);
+
+        let first = start as usize;
+        if let Some(shadow) = &mut self.shadow {
+            for (reg, wrote) in Config::ALL[first..first + data.len()].iter().zip(data) {
+                shadow.store(*reg, *wrote);
+            }
+        }
+
+        if self.verify {
+            for (reg, wrote) in Config::ALL[first..first + data.len()].iter().zip(data) {
+                self.verify_register((*reg).into(), *wrote)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Read or write the power amplifier table using burst access, starting at index 0.
+    pub fn access_patable(
+        &mut self,
+        access: access::Access,
+        data: &mut [u8],
+    ) -> Result<(), Error<SpiE>> {
+        let mut buffer = [MultiByte::PATABLE.addr(access, access::Mode::Burst)];
+
+        self.spi.transaction(&mut [
+            Operation::TransferInPlace(&mut buffer),
+            Operation::TransferInPlace(data),
+        ])?;
+        spi_trace!("{} PATABLE {=[u8]:02x}", access, data);
+
+        self.update_status(buffer[0], access);
+        Ok(())
+    }
+
+    /// Write a command strobe. Dirty registers of the shadow copy are written first, except for
+    /// SRES, which resets the shadow copy, and SNOP.
+    pub fn write_cmd_strobe(&mut self, cmd: Command) -> Result<(), Error<SpiE>> {
+        if !matches!(cmd, Command::SRES | Command::SNOP) {
+            self.flush_shadow()?;
+        }
+
         let mut buffer = [cmd.addr(access::Access::Write, access::Mode::Single)];
 
         self.spi.transfer_in_place(&mut buffer)?;
+        spi_trace!("strobe {}", cmd);
 
-        if cmd == Command::SNOP {
-            // SNOP is the only command with no effect and therefore can be used to get access to the chip status byte
-            self.status = Some(StatusByte::from(buffer[0]));
-        } else {
-            // Discard returned chip status byte in `buffer[0]` as most probably, it will reflect the previous state
-            // Set status to `None`, to inform the user about lack of valid state read
-            self.status = None;
+        // The returned chip status byte reflects the state before the command strobe took effect
+        self.update_status(buffer[0], access::Access::Write);
+
+        if let Some(shadow) = &mut self.shadow {
+            match cmd {
+                Command::SRES => *shadow = Shadow::from_reset_values(),
+                // TEST2, TEST1 and TEST0 are not retained in SLEEP
+                Command::SPWD => {
+                    shadow.invalidate(Config::TEST2);
+                    shadow.invalidate(Config::TEST1);
+                    shadow.invalidate(Config::TEST0);
+                }
+                _ => {}
+            }
         }
         Ok(())
     }
 
-    pub fn write_register<R>(&mut self, reg: R, byte: u8) -> Result<(), SpiE>
+    /// Write the dirty registers of the shadow copy to the chip, using as few bursts as possible.
+    pub fn flush_shadow(&mut self) -> Result<(), Error<SpiE>> {
+        let Some(shadow) = self.shadow else {
+            return Ok(());
+        };
+
+        let mut from = 0;
+        while let Some((start, data)) = shadow.next_burst(from) {
+            self.write_burst(start, data)?;
+            from = start as usize + data.len();
+        }
+        Ok(())
+    }
+
+    /// Read the chip status byte using the SNOP command strobe. Read access returns the number of
+    /// bytes available in the RX FIFO, write access the number of free bytes in the TX FIFO.
+    pub fn read_chip_status(&mut self, access: access::Access) -> Result<StatusByte, Error<SpiE>> {
+        let mut buffer = [Command::SNOP.addr(access, access::Mode::Single)];
+
+        self.spi.transfer_in_place(&mut buffer)?;
+        spi_trace!("strobe {} ({})", Command::SNOP, access);
+
+        Ok(self.update_status(buffer[0], access))
+    }
+
+    /// Read the chip status byte with SNOP after keeping CSn asserted for `delay_ns`, e.g. to wake
+    /// the chip from SLEEP and give the crystal oscillator time to start before clocking SPI.
+    pub fn read_chip_status_delayed(&mut self, delay_ns: u32) -> Result<StatusByte, Error<SpiE>> {
+        let mut buffer = [Command::SNOP.addr(access::Access::Write, access::Mode::Single)];
+
+        self.spi.transaction(&mut [
+            Operation::DelayNs(delay_ns),
+            Operation::TransferInPlace(&mut buffer),
+        ])?;
+        spi_trace!("strobe {} after {=u32} ns", Command::SNOP, delay_ns);
+
+        Ok(self.update_status(buffer[0], access::Access::Write))
+    }
+
+    fn update_status(&mut self, byte: u8, access: access::Access) -> StatusByte {
+        let status = StatusByte::new(byte, access);
+        spi_trace!("status {}", status);
+        self.status = Some(status);
+        if let Some(observer) = self.status_observer.as_mut() {
+            observer(status);
+        }
+        status
+    }
+
+    /// Write a register, configuration registers are only written to the shadow copy when
+    /// enabled, and marked dirty.
+    pub fn write_register<R>(&mut self, reg: R, byte: u8) -> Result<(), Error<SpiE>>
     where
         R: Into<Register>,
     {
-        let mut buffer = [reg.into().waddr(access::Mode::Single), byte];
+        let reg = reg.into();
+        if let (Register::Config(config), Some(shadow)) = (reg, &mut self.shadow) {
+            shadow.set(config, byte);
+            return Ok(());
+        }
+
+        let mut buffer = [reg.waddr(access::Mode::Single), byte];
 
         self.spi.transfer_in_place(&mut buffer)?;
+        spi_trace!("write {} = {=u8:#04x}", reg, byte);
+
+        self.update_status(buffer[0], access::Access::Write);
+
+        if self.verify {
+            self.verify_register(reg, byte)?;
+        }
+        Ok(())
+    }
 
-        self.status = Some(StatusByte::from(buffer[0]));
+    /// Read back a written configuration register and compare it with the written value.
+    /// Registers updated by the chip itself, such as the calibration results, are not verified.
+    fn verify_register(&mut self, reg: Register, wrote: u8) -> Result<(), Error<SpiE>> {
+        if let Register::Config(config) = reg {
+            if !config.updated_by_chip() {
+                let read = self.read_register_spi(reg)?;
+                if read != wrote {
+                    return Err(Error::VerifyFailed {
+                        reg: config,
+                        wrote,
+                        read,
+                    });
+                }
+            }
+        }
         Ok(())
     }
 
-    pub fn modify_register<R, F>(&mut self, reg: R, f: F) -> Result<(), SpiE>
+    pub fn modify_register<R, F>(&mut self, reg: R, f: F) -> Result<(), Error<SpiE>>
     where
         R: Into<Register> + Copy,
         F: FnOnce(u8) -> u8,
@@ -116,3 +332,98 @@ where
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock::Chip;
+
+    fn cc1101() -> Cc1101<Chip> {
+        let mut cc1101 = Cc1101::new(Chip::new()).unwrap();
+        cc1101.verify = true;
+        cc1101
+    }
+
+    #[test]
+    fn test_verify() {
+        let mut cc1101 = cc1101();
+        cc1101.write_register(Config::CHANNR, 0x12).unwrap();
+        cc1101.modify_register(Config::MCSM0, |r| r | 0x30).unwrap();
+        cc1101.write_burst(Config::FREQ2, &[0x10, 0xA7, 0x62]).unwrap();
+        assert_eq!(cc1101.spi.get(Config::CHANNR), 0x12);
+        assert_eq!(cc1101.spi.get(Config::MCSM0), 0x34);
+        assert_eq!(cc1101.spi.get(Config::FREQ0), 0x62);
+    }
+
+    #[test]
+    fn test_verify_failed() {
+        let mut cc1101 = cc1101();
+        cc1101.spi.flip[Config::CHANNR as usize] = 0x01;
+        cc1101.spi.flip[Config::MCSM0 as usize] = 0x80;
+        cc1101.spi.flip[Config::FREQ1 as usize] = 0x04;
+        let failed = |reg, wrote, read| {
+            Err(Error::VerifyFailed {
+                reg,
+                wrote,
+                read,
+            })
+        };
+        assert_eq!(cc1101.write_register(Config::CHANNR, 0x12), failed(Config::CHANNR, 0x12, 0x13));
+        assert_eq!(
+            cc1101.modify_register(Config::MCSM0, |r| r | 0x30),
+            failed(Config::MCSM0, 0x34, 0xB4)
+        );
+        assert_eq!(
+            cc1101.write_burst(Config::FREQ2, &[0x10, 0xA7, 0x62]),
+            failed(Config::FREQ1, 0xA7, 0xA3)
+        );
+
+        cc1101.verify = false;
+        assert_eq!(cc1101.write_register(Config::CHANNR, 0x12), Ok(()));
+    }
+
+    #[test]
+    fn test_verify_skips_calibration_results() {
+        let mut cc1101 = cc1101();
+        cc1101.spi.flip[Config::FSCAL1 as usize] = 0x01;
+        cc1101.write_register(Config::FSCAL1, 0x20).unwrap();
+        cc1101.write_burst(Config::FSCAL3, &[0xE9, 0x2A, 0x20]).unwrap();
+        // Only the writes, the calibration results are not read back
+        assert_eq!(cc1101.spi.status_bytes.len(), 2);
+        assert_eq!(cc1101.spi.get(Config::FSCAL1), 0x21);
+    }
+
+    #[test]
+    fn test_read_status_stable() {
+        let mut cc1101 = cc1101();
+        cc1101.spi.queue_status(Status::RXBYTES, &[5, 6, 6]);
+        assert_eq!(cc1101.read_status_stable(Status::RXBYTES), Ok(6));
+        assert_eq!(cc1101.spi.status_bytes.len(), 3);
+
+        cc1101.status_retries = 2;
+        cc1101.spi.queue_status(Status::MARCSTATE, &[0x0D, 0x0E, 0x0F, 0x01]);
+        assert_eq!(
+            cc1101.read_status_stable(Status::MARCSTATE),
+            Err(Error::UnstableStatus(Status::MARCSTATE))
+        );
+    }
+
+    #[test]
+    fn test_fifo_bytes_in_status_byte() {
+        let mut cc1101 = cc1101();
+        cc1101.spi.tx_fifo.extend([0; 60]);
+        cc1101.spi.rx_fifo.extend([0; 20]);
+
+        let status = cc1101.read_chip_status(access::Access::Write).unwrap();
+        assert_eq!((status.tx_bytes_free(), status.rx_bytes_available()), (Some(4), None));
+        let status = cc1101.read_chip_status(access::Access::Read).unwrap();
+        // Saturates at 15
+        assert_eq!((status.tx_bytes_free(), status.rx_bytes_available()), (None, Some(15)));
+
+        // Every transaction updates the last status byte, with its access direction
+        cc1101.access_fifo(access::Access::Write, &mut [], &mut [0; 4]).unwrap();
+        assert_eq!(cc1101.status.and_then(|s| s.tx_bytes_free()), Some(4));
+        cc1101.write_cmd_strobe(Command::SNOP).unwrap();
+        assert_eq!(cc1101.status.and_then(|s| s.tx_bytes_free()), Some(0));
+    }
+}
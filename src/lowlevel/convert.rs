@@ -61,6 +61,7 @@ pub fn from_rssi_to_rssi_dbm(rssi: u8) -> i16 {
 #[cfg(test)]
 mod tests {
     use crate::lowlevel::convert::*;
+    use crate::lowlevel::types::{AutoCalibration, CloseInRx, FifoThreshold, PoTimeout};
     use crate::lowlevel::FXOSC;
 
     #[test]
@@ -76,7 +77,7 @@ mod tests {
         assert_eq!(from_frequency_offset(0), 0);
         assert_eq!(from_frequency_offset(1586 + 1), 1);
         assert_eq!(from_frequency_offset(201538 + 1), 127);
-        assert_eq!(from_frequency_offset(-203125 + 0), 128);
+        assert_eq!(from_frequency_offset(-203125), 128);
         assert_eq!(from_frequency_offset(-1586 - 1), 255);
 
         assert_eq!(to_frequency_offset(0), 0);
@@ -90,7 +91,7 @@ mod tests {
     fn test_deviation() {
         // f_dev = f_osc / 2^17 * (8 + DEVIATION_M) * 2^DEVIATION_E
         fn calc_rev_dev(dev_m: u8, dev_e: u8) -> u64 {
-            (((FXOSC as f32 / (2u64.pow(17) as f32)) as f32)
+            ((FXOSC as f32 / (2u64.pow(17) as f32))
                 * (8f32 + dev_m as f32)
                 * (2u64.pow(dev_e as u32) as f32)) as u64
         }
@@ -161,4 +162,41 @@ mod tests {
         assert_eq!(from_freq_if(203_125), 0x08);
         assert_eq!(from_freq_if(152_300), 0x06);
     }
+
+    #[test]
+    fn test_close_in_rx() {
+        assert_eq!(CloseInRx::from(0), CloseInRx::Db0);
+        assert_eq!(CloseInRx::from(3), CloseInRx::Db18);
+        for value in 0..4 {
+            assert_eq!(u8::from(CloseInRx::from(value)), value);
+        }
+    }
+
+    #[test]
+    fn test_fifo_threshold() {
+        assert_eq!(FifoThreshold::from(0x00), FifoThreshold::TX_61_RX_4);
+        assert_eq!(FifoThreshold::from(0x07), FifoThreshold::TX_33_RX_32);
+        assert_eq!(FifoThreshold::from(0x0F), FifoThreshold::TX_1_RX_64);
+        for value in 0..16 {
+            assert_eq!(u8::from(FifoThreshold::from(value)), value);
+        }
+    }
+
+    #[test]
+    fn test_po_timeout() {
+        assert_eq!(PoTimeout::from(0), PoTimeout::EXPIRE_COUNT_1);
+        assert_eq!(PoTimeout::from(3), PoTimeout::EXPIRE_COUNT_256);
+        for value in 0..4 {
+            assert_eq!(u8::from(PoTimeout::from(value)), value);
+        }
+    }
+
+    #[test]
+    fn test_autocalibration() {
+        assert_eq!(AutoCalibration::from(0), AutoCalibration::Disabled);
+        assert_eq!(AutoCalibration::from(3), AutoCalibration::ToIdleEvery4th);
+        for value in 0..4 {
+            assert_eq!(u8::from(AutoCalibration::from(value)), value);
+        }
+    }
 }
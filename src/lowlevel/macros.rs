@@ -1,3 +1,11 @@
+/// Log an SPI access at trace level, with the `defmt-trace` feature.
+macro_rules! spi_trace {
+    ($($arg:tt)+) => {
+        #[cfg(feature = "defmt-trace")]
+        defmt::trace!($($arg)+);
+    };
+}
+
 macro_rules! register {
     ($REGISTER:ident, $reset_value:expr, $uxx:ty, {
         $(#[$($attr:tt)*] $bitfield:ident @ $range:expr,)+
@@ -20,7 +28,7 @@ macro_rules! register {
 
                     let offset = $range.offset();
                     let size = $range.size();
-                    (((1 << size.to_wider()) - 1)) << offset
+                    ((1 << size.to_wider()) - 1) << offset
                 }
             )+
         }
@@ -83,6 +91,7 @@ macro_rules! register {
 }
 
 #[cfg(test)]
+#[allow(dead_code)]
 mod tests {
 
     fn bit_mask(bits: u8) -> u8 {
@@ -13,6 +13,7 @@ pub use self::status_byte::*;
 use crate::lowlevel::access;
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Register {
     Command(command::Command),
     Config(config::Config),
@@ -1,4 +1,5 @@
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Command {
     /// Reset chip.
     SRES = 0x30,
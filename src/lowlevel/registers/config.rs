@@ -1,4 +1,5 @@
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Config {
     /// GDO2 output pin configuration
     IOCFG2 = 0x00,
@@ -97,6 +98,115 @@ pub enum Config {
 }
 
 impl Config {
+    /// All configuration registers, ordered by address.
+    pub const ALL: [Config; 47] = [
+        Config::IOCFG2,
+        Config::IOCFG1,
+        Config::IOCFG0,
+        Config::FIFOTHR,
+        Config::SYNC1,
+        Config::SYNC0,
+        Config::PKTLEN,
+        Config::PKTCTRL1,
+        Config::PKTCTRL0,
+        Config::ADDR,
+        Config::CHANNR,
+        Config::FSCTRL1,
+        Config::FSCTRL0,
+        Config::FREQ2,
+        Config::FREQ1,
+        Config::FREQ0,
+        Config::MDMCFG4,
+        Config::MDMCFG3,
+        Config::MDMCFG2,
+        Config::MDMCFG1,
+        Config::MDMCFG0,
+        Config::DEVIATN,
+        Config::MCSM2,
+        Config::MCSM1,
+        Config::MCSM0,
+        Config::FOCCFG,
+        Config::BSCFG,
+        Config::AGCCTRL2,
+        Config::AGCCTRL1,
+        Config::AGCCTRL0,
+        Config::WOREVT1,
+        Config::WOREVT0,
+        Config::WORCTRL,
+        Config::FREND1,
+        Config::FREND0,
+        Config::FSCAL3,
+        Config::FSCAL2,
+        Config::FSCAL1,
+        Config::FSCAL0,
+        Config::RCCTRL1,
+        Config::RCCTRL0,
+        Config::FSTEST,
+        Config::PTEST,
+        Config::AGCTEST,
+        Config::TEST2,
+        Config::TEST1,
+        Config::TEST0,
+    ];
+
+    /// Registers holding values written by the chip itself, e.g. frequency synthesizer calibration results.
+    pub fn updated_by_chip(&self) -> bool {
+        matches!(self, Config::FSCAL3 | Config::FSCAL2 | Config::FSCAL1)
+    }
+
+    /// Value of the register after a chip reset.
+    pub fn reset_value(&self) -> u8 {
+        match self {
+            Config::IOCFG2 => IOCFG2::default().bits(),
+            Config::IOCFG1 => IOCFG1::default().bits(),
+            Config::IOCFG0 => IOCFG0::default().bits(),
+            Config::FIFOTHR => FIFOTHR::default().bits(),
+            Config::SYNC1 => SYNC1::default().bits(),
+            Config::SYNC0 => SYNC0::default().bits(),
+            Config::PKTLEN => PKTLEN::default().bits(),
+            Config::PKTCTRL1 => PKTCTRL1::default().bits(),
+            Config::PKTCTRL0 => PKTCTRL0::default().bits(),
+            Config::ADDR => ADDR::default().bits(),
+            Config::CHANNR => CHANNR::default().bits(),
+            Config::FSCTRL1 => FSCTRL1::default().bits(),
+            Config::FSCTRL0 => FSCTRL0::default().bits(),
+            Config::FREQ2 => FREQ2::default().bits(),
+            Config::FREQ1 => FREQ1::default().bits(),
+            Config::FREQ0 => FREQ0::default().bits(),
+            Config::MDMCFG4 => MDMCFG4::default().bits(),
+            Config::MDMCFG3 => MDMCFG3::default().bits(),
+            Config::MDMCFG2 => MDMCFG2::default().bits(),
+            Config::MDMCFG1 => MDMCFG1::default().bits(),
+            Config::MDMCFG0 => MDMCFG0::default().bits(),
+            Config::DEVIATN => DEVIATN::default().bits(),
+            Config::MCSM2 => MCSM2::default().bits(),
+            Config::MCSM1 => MCSM1::default().bits(),
+            Config::MCSM0 => MCSM0::default().bits(),
+            Config::FOCCFG => FOCCFG::default().bits(),
+            Config::BSCFG => BSCFG::default().bits(),
+            Config::AGCCTRL2 => AGCCTRL2::default().bits(),
+            Config::AGCCTRL1 => AGCCTRL1::default().bits(),
+            Config::AGCCTRL0 => AGCCTRL0::default().bits(),
+            Config::WOREVT1 => WOREVT1::default().bits(),
+            Config::WOREVT0 => WOREVT0::default().bits(),
+            Config::WORCTRL => WORCTRL::default().bits(),
+            Config::FREND1 => FREND1::default().bits(),
+            Config::FREND0 => FREND0::default().bits(),
+            Config::FSCAL3 => FSCAL3::default().bits(),
+            Config::FSCAL2 => FSCAL2::default().bits(),
+            Config::FSCAL1 => FSCAL1::default().bits(),
+            Config::FSCAL0 => FSCAL0::default().bits(),
+            Config::RCCTRL1 => RCCTRL1::default().bits(),
+            Config::RCCTRL0 => RCCTRL0::default().bits(),
+            Config::FSTEST => FSTEST::default().bits(),
+            Config::PTEST => PTEST::default().bits(),
+            Config::AGCTEST => AGCTEST::default().bits(),
+            Config::TEST2 => TEST2::default().bits(),
+            Config::TEST1 => TEST1::default().bits(),
+            Config::TEST0 => TEST0::default().bits(),
+        }
+    }
+
     pub fn addr(
         &self,
         access: crate::lowlevel::access::Access,
@@ -1,4 +1,5 @@
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum MultiByte {
     /// Power Amplifier Table
     PATABLE = 0x3E,
@@ -1,6 +1,9 @@
+use crate::lowlevel::access::Access;
+
 /// Indicates the current main state machine mode
 #[allow(non_camel_case_types)]
 #[derive(Default, Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum State {
     /// IDLE state (Also reported for some transitional states instead of SETTLING or CALIBRATE)
     #[default]
@@ -38,24 +41,54 @@ impl From<u8> for State {
 }
 
 /// Table 23: Status Byte
-#[derive(Default, Clone, Copy)]
+#[derive(Default, Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct StatusByte {
     pub chip_rdy: bool,
     pub state: State,
+    /// Bytes available in the RX FIFO on read access, free bytes in the TX FIFO on write access.
+    /// Saturates at 15, meaning 15 or more.
     pub fifo_bytes_available: u8,
+    /// Access direction (R/W bit in the header byte) of the SPI transaction returning the status.
+    pub access: Access,
 }
 
-impl From<u8> for StatusByte {
-    fn from(value: u8) -> Self {
+impl StatusByte {
+    /// Decode a status byte returned by an SPI transaction with the given access direction.
+    pub fn new(value: u8, access: Access) -> Self {
         let status_byte = STATUS_BYTE(value);
         StatusByte {
             chip_rdy: (status_byte.chip_rdyn() == 0),
             state: State::from(status_byte.state()),
             fifo_bytes_available: status_byte.fifo_bytes_available(),
+            access,
+        }
+    }
+
+    /// Number of bytes available in the RX FIFO, if the status was returned on read access.
+    pub fn rx_bytes_available(&self) -> Option<u8> {
+        match self.access {
+            Access::Read => Some(self.fifo_bytes_available),
+            Access::Write => None,
+        }
+    }
+
+    /// Number of free bytes in the TX FIFO, if the status was returned on write access.
+    pub fn tx_bytes_free(&self) -> Option<u8> {
+        match self.access {
+            Access::Read => None,
+            Access::Write => Some(self.fifo_bytes_available),
         }
     }
 }
 
+/// Decode a status byte returned on read access.
+impl From<u8> for StatusByte {
+    fn from(value: u8) -> Self {
+        StatusByte::new(value, Access::Read)
+    }
+}
+
 register!(STATUS_BYTE, 0b1000_0000, u8, {
     #[doc = "Stays high until power and crystal have stabilized. Should always be low when using the SPI interface."]
     chip_rdyn @ 7,
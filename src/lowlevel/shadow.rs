@@ -0,0 +1,148 @@
+//! Shadow copy of the configuration registers.
+
+use crate::lowlevel::registers::Config;
+
+const NUM_REGISTERS: usize = Config::ALL.len();
+
+/// Configuration register values held in RAM, with the registers not yet written to the chip
+/// marked dirty.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Shadow {
+    values: [u8; NUM_REGISTERS],
+    dirty: u64,
+}
+
+impl Shadow {
+    /// Shadow copy holding the reset values, matching a chip that has just been reset.
+    pub fn from_reset_values() -> Self {
+        let mut values = [0; NUM_REGISTERS];
+        for (value, reg) in values.iter_mut().zip(Config::ALL.iter()) {
+            *value = reg.reset_value();
+        }
+        Shadow {
+            values,
+            dirty: 0,
+        }
+    }
+
+    /// Shadow copy holding the given values, e.g. from a burst read of all registers.
+    pub fn from_values(values: [u8; NUM_REGISTERS]) -> Self {
+        Shadow {
+            values,
+            dirty: 0,
+        }
+    }
+
+    pub fn get(&self, reg: Config) -> u8 {
+        self.values[reg as usize]
+    }
+
+    /// Store a value to be written to the chip, marking the register dirty if it changed.
+    pub fn set(&mut self, reg: Config, value: u8) {
+        if self.values[reg as usize] != value {
+            self.values[reg as usize] = value;
+            self.dirty |= 1 << reg as u8;
+        }
+    }
+
+    /// Store a value written to the chip.
+    pub fn store(&mut self, reg: Config, value: u8) {
+        self.values[reg as usize] = value;
+        self.dirty &= !(1 << reg as u8);
+    }
+
+    /// Mark a register dirty, e.g. because the chip lost its value.
+    pub fn invalidate(&mut self, reg: Config) {
+        self.dirty |= 1 << reg as u8;
+    }
+
+    pub fn is_dirty(&self, reg: Config) -> bool {
+        self.dirty & (1 << reg as u8) != 0
+    }
+
+    pub fn clear_dirty(&mut self) {
+        self.dirty = 0;
+    }
+
+    pub fn values(&self) -> &[u8; NUM_REGISTERS] {
+        &self.values
+    }
+
+    /// Next range of registers, starting at or after index `from`, to be written with one burst.
+    /// The range starts and ends with a dirty register, and includes clean registers in between,
+    /// except registers updated by the chip, whose shadow value may be outdated.
+    pub fn next_burst(&self, from: usize) -> Option<(Config, &[u8])> {
+        let start = (from..NUM_REGISTERS).find(|&i| self.is_dirty(Config::ALL[i]))?;
+        let mut end = start + 1;
+
+        for (i, reg) in Config::ALL.iter().enumerate().skip(start + 1) {
+            if self.is_dirty(*reg) {
+                end = i + 1;
+            } else if reg.updated_by_chip() {
+                break;
+            }
+        }
+        Some((Config::ALL[start], &self.values[start..end]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bursts(shadow: &Shadow) -> [(usize, usize); 4] {
+        let mut bursts = [(0, 0); 4];
+        let mut from = 0;
+        let mut n = 0;
+        while let Some((start, data)) = shadow.next_burst(from) {
+            bursts[n] = (start as usize, data.len());
+            from = start as usize + data.len();
+            n += 1;
+        }
+        bursts
+    }
+
+    #[test]
+    fn test_reset_values() {
+        let shadow = Shadow::from_reset_values();
+        assert_eq!(shadow.get(Config::IOCFG2), 0x29);
+        assert_eq!(shadow.get(Config::PKTCTRL0), 0x45);
+        assert_eq!(shadow.get(Config::MDMCFG4), 0x8C);
+        assert_eq!(shadow.get(Config::TEST0), 0x0B);
+        assert_eq!(shadow.next_burst(0), None);
+    }
+
+    #[test]
+    fn test_set_marks_changed_registers_dirty() {
+        let mut shadow = Shadow::from_reset_values();
+        shadow.set(Config::PKTLEN, 0xFF);
+        assert!(!shadow.is_dirty(Config::PKTLEN));
+        shadow.set(Config::PKTLEN, 0x20);
+        assert!(shadow.is_dirty(Config::PKTLEN));
+        shadow.store(Config::PKTLEN, 0x20);
+        assert!(!shadow.is_dirty(Config::PKTLEN));
+    }
+
+    #[test]
+    fn test_bursts_span_clean_registers() {
+        let mut shadow = Shadow::from_reset_values();
+        shadow.set(Config::MDMCFG4, 0xF5);
+        shadow.set(Config::MDMCFG3, 0x83);
+        shadow.set(Config::DEVIATN, 0x15);
+        assert_eq!(bursts(&shadow), [(0x10, 6), (0, 0), (0, 0), (0, 0)]);
+    }
+
+    #[test]
+    fn test_bursts_skip_clean_calibration_registers() {
+        let mut shadow = Shadow::from_reset_values();
+        shadow.set(Config::FREND0, 0x11);
+        shadow.set(Config::FSCAL0, 0x1F);
+        shadow.invalidate(Config::TEST2);
+        shadow.invalidate(Config::TEST0);
+        assert_eq!(bursts(&shadow), [(0x22, 1), (0x26, 9), (0, 0), (0, 0)]);
+
+        shadow.set(Config::FSCAL2, 0x2A);
+        assert_eq!(bursts(&shadow), [(0x22, 1), (0x24, 1), (0x26, 9), (0, 0)]);
+    }
+}
@@ -1,29 +1,49 @@
 mod address_check;
 mod auto_calibration;
+mod carrier_sense;
 mod cca_mode;
+mod chip_variant;
+mod close_in_rx;
 mod fifo_threshold;
 mod filter_length;
+mod fs_cal;
 mod gdo_cfg;
+mod gdo_pin;
 mod length_config;
 mod machine_state;
 mod mod_format;
 mod num_preamble;
+mod packet_format;
 mod packet_status;
 mod po_timeout;
+mod rxoff_mode;
+mod shadow_init;
+mod sleep_context;
 mod sync_check;
 mod target_amplitude;
+mod txoff_mode;
 
 pub use self::address_check::*;
 pub use self::auto_calibration::*;
+pub use self::carrier_sense::*;
 pub use self::cca_mode::*;
+pub use self::chip_variant::*;
+pub use self::close_in_rx::*;
 pub use self::fifo_threshold::*;
 pub use self::filter_length::*;
+pub use self::fs_cal::*;
 pub use self::gdo_cfg::*;
+pub use self::gdo_pin::*;
 pub use self::length_config::*;
 pub use self::machine_state::*;
 pub use self::mod_format::*;
 pub use self::num_preamble::*;
+pub use self::packet_format::*;
 pub use self::packet_status::*;
 pub use self::po_timeout::*;
+pub use self::rxoff_mode::*;
+pub use self::shadow_init::*;
+pub use self::sleep_context::*;
 pub use self::sync_check::*;
 pub use self::target_amplitude::*;
+pub use self::txoff_mode::*;
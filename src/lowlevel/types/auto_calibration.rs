@@ -1,5 +1,6 @@
 /// Configure what state transitions result in auto-calibration.
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[repr(u8)]
 pub enum AutoCalibration {
     /// Never (manually calibrate using SCAL strobe).
@@ -17,3 +18,15 @@ impl From<AutoCalibration> for u8 {
         value as Self
     }
 }
+
+impl From<u8> for AutoCalibration {
+    fn from(value: u8) -> Self {
+        match value {
+            0x00 => AutoCalibration::Disabled,
+            0x01 => AutoCalibration::FromIdle,
+            0x02 => AutoCalibration::ToIdle,
+            0x03 => AutoCalibration::ToIdleEvery4th,
+            _ => panic!("Unknown value: {}", value),
+        }
+    }
+}
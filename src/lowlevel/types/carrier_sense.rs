@@ -0,0 +1,20 @@
+/// Relative carrier sense threshold, increase in RSSI that asserts carrier sense.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[repr(u8)]
+pub enum CarrierSenseRelThr {
+    /// Relative carrier sense threshold disabled.
+    Disabled = 0,
+    /// 6 dB increase in RSSI value.
+    Db6 = 1,
+    /// 10 dB increase in RSSI value.
+    Db10 = 2,
+    /// 14 dB increase in RSSI value.
+    Db14 = 3,
+}
+
+impl From<CarrierSenseRelThr> for u8 {
+    fn from(value: CarrierSenseRelThr) -> Self {
+        value as Self
+    }
+}
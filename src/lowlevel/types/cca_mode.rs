@@ -1,5 +1,6 @@
 /// Clear Channel Assessment Mode.
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[repr(u8)]
 pub enum CcaMode {
     /// Clear channel indication: Always
@@ -0,0 +1,125 @@
+/// Members of the CC1101 family sharing the same register map.
+#[allow(non_camel_case_types)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ChipVariant {
+    /// CC1100, the predecessor of the CC1101.
+    CC1100,
+    /// CC1101 Sub-1GHz RF transceiver.
+    CC1101,
+    /// CC110L value line transceiver, no Wake-on-Radio.
+    CC110L,
+    /// CC113L value line receiver, no TX and no Wake-on-Radio.
+    CC113L,
+    /// CC115L value line transmitter, no RX and no Wake-on-Radio.
+    CC115L,
+}
+
+/// Chip Variant Error (Unknown Chip), reported for unrecognized PARTNUM / VERSION combinations.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ChipVariantError {
+    /// PARTNUM and VERSION of the unknown chip.
+    UnknownChip(u8, u8),
+}
+
+impl ChipVariant {
+    /// Feature set of the chip variant.
+    pub fn capabilities(self) -> Capabilities {
+        match self {
+            ChipVariant::CC1100 | ChipVariant::CC1101 => Capabilities {
+                receive: true,
+                transmit: true,
+                wake_on_radio: true,
+            },
+            ChipVariant::CC110L => Capabilities {
+                receive: true,
+                transmit: true,
+                wake_on_radio: false,
+            },
+            ChipVariant::CC113L => Capabilities {
+                receive: true,
+                transmit: false,
+                wake_on_radio: false,
+            },
+            ChipVariant::CC115L => Capabilities {
+                receive: false,
+                transmit: true,
+                wake_on_radio: false,
+            },
+        }
+    }
+}
+
+/// Identify the chip variant from the PARTNUM and VERSION status registers.
+///
+/// The value line parts (CC110L, CC113L, CC115L) report the same PARTNUM / VERSION pair and are
+/// all identified as CC110L. A CC113L or CC115L has to be selected explicitly.
+impl TryFrom<(u8, u8)> for ChipVariant {
+    type Error = ChipVariantError;
+
+    fn try_from(value: (u8, u8)) -> Result<Self, Self::Error> {
+        match value {
+            (0x00, 0x03) => Ok(ChipVariant::CC1100),
+            (0x00, 0x04) | (0x00, 0x14) => Ok(ChipVariant::CC1101),
+            (0x00, 0x07) => Ok(ChipVariant::CC110L),
+            (partnum, version) => Err(ChipVariantError::UnknownChip(partnum, version)),
+        }
+    }
+}
+
+/// Optional features that are not available on every chip variant.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Capability {
+    /// Receive path (RX state, RX FIFO).
+    Receive,
+    /// Transmit path (TX state, TX FIFO).
+    Transmit,
+    /// Wake-on-Radio and the associated RC oscillator timer.
+    WakeOnRadio,
+}
+
+/// Set of features supported by a chip variant.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Capabilities {
+    pub receive: bool,
+    pub transmit: bool,
+    pub wake_on_radio: bool,
+}
+
+impl Capabilities {
+    /// Check whether the given feature is supported.
+    pub fn supports(&self, capability: Capability) -> bool {
+        match capability {
+            Capability::Receive => self.receive,
+            Capability::Transmit => self.transmit,
+            Capability::WakeOnRadio => self.wake_on_radio,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identify() {
+        let cases = [
+            ((0x00, 0x03), Ok(ChipVariant::CC1100)),
+            ((0x00, 0x04), Ok(ChipVariant::CC1101)),
+            ((0x00, 0x14), Ok(ChipVariant::CC1101)),
+            ((0x00, 0x07), Ok(ChipVariant::CC110L)),
+            ((0x00, 0x00), Err(ChipVariantError::UnknownChip(0x00, 0x00))),
+            ((0x00, 0x05), Err(ChipVariantError::UnknownChip(0x00, 0x05))),
+            ((0x00, 0xFF), Err(ChipVariantError::UnknownChip(0x00, 0xFF))),
+            ((0x80, 0x03), Err(ChipVariantError::UnknownChip(0x80, 0x03))),
+            ((0x01, 0x14), Err(ChipVariantError::UnknownChip(0x01, 0x14))),
+            ((0xFF, 0xFF), Err(ChipVariantError::UnknownChip(0xFF, 0xFF))),
+        ];
+        for (hw_info, expected) in cases {
+            assert_eq!(ChipVariant::try_from(hw_info), expected, "{:?}", hw_info);
+        }
+    }
+}
@@ -0,0 +1,32 @@
+/// RX attenuation for close-in reception, see DN010.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[repr(u8)]
+pub enum CloseInRx {
+    /// 0 dB RX attenuation.
+    Db0 = 0,
+    /// 6 dB RX attenuation.
+    Db6 = 1,
+    /// 12 dB RX attenuation.
+    Db12 = 2,
+    /// 18 dB RX attenuation.
+    Db18 = 3,
+}
+
+impl From<CloseInRx> for u8 {
+    fn from(value: CloseInRx) -> Self {
+        value as Self
+    }
+}
+
+impl From<u8> for CloseInRx {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => CloseInRx::Db0,
+            1 => CloseInRx::Db6,
+            2 => CloseInRx::Db12,
+            3 => CloseInRx::Db18,
+            _ => panic!("Unknown value: {}", value),
+        }
+    }
+}
@@ -1,6 +1,7 @@
 /// TX FIFO and RX FIFO threshold configuration.
 #[allow(non_camel_case_types)]
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[repr(u8)]
 pub enum FifoThreshold {
     /// 61 bytes in TX, 4 bytes in RX.
@@ -42,3 +43,27 @@ impl From<FifoThreshold> for u8 {
         value as Self
     }
 }
+
+impl From<u8> for FifoThreshold {
+    fn from(value: u8) -> Self {
+        match value {
+            0x00 => FifoThreshold::TX_61_RX_4,
+            0x01 => FifoThreshold::TX_57_RX_8,
+            0x02 => FifoThreshold::TX_53_RX_12,
+            0x03 => FifoThreshold::TX_49_RX_16,
+            0x04 => FifoThreshold::TX_45_RX_20,
+            0x05 => FifoThreshold::TX_41_RX_24,
+            0x06 => FifoThreshold::TX_37_RX_28,
+            0x07 => FifoThreshold::TX_33_RX_32,
+            0x08 => FifoThreshold::TX_29_RX_36,
+            0x09 => FifoThreshold::TX_25_RX_40,
+            0x0A => FifoThreshold::TX_21_RX_44,
+            0x0B => FifoThreshold::TX_17_RX_48,
+            0x0C => FifoThreshold::TX_13_RX_52,
+            0x0D => FifoThreshold::TX_9_RX_56,
+            0x0E => FifoThreshold::TX_5_RX_60,
+            0x0F => FifoThreshold::TX_1_RX_64,
+            _ => panic!("Unknown value: {}", value),
+        }
+    }
+}
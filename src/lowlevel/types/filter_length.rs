@@ -1,5 +1,6 @@
 /// Filter length for frequency and amplitude modulations.
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[repr(u8)]
 pub enum FilterLength {
     FrequencyModulation(ChannelFilterSamples),
@@ -18,6 +19,7 @@ impl From<FilterLength> for u8 {
 /// Channel filter samples.
 /// 2-FSK, 4-FSK, MSK: Sets the averaging length for the amplitude from the channel filter.
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[repr(u8)]
 pub enum ChannelFilterSamples {
     /// 8 samples
@@ -39,6 +41,7 @@ impl From<ChannelFilterSamples> for u8 {
 /// OOK/ASK decision boundary.
 /// ASK, OOK: Sets the OOK/ASK decision boundary for OOK/ASK reception.
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[repr(u8)]
 pub enum DecisionBoundary {
     /// 4 dB
@@ -0,0 +1,8 @@
+/// Frequency synthesizer calibration results, as held by FSCAL3, FSCAL2 and FSCAL1.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct FsCal {
+    pub fscal3: u8,
+    pub fscal2: u8,
+    pub fscal1: u8,
+}
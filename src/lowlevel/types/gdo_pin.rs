@@ -0,0 +1,7 @@
+/// GDO pin usable as a radio output, GDO1 is shared with the SPI SO line.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum GdoPin {
+    Gdo0,
+    Gdo2,
+}
@@ -1,6 +1,7 @@
 /// Packet length configuration.
 #[allow(non_camel_case_types)]
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[repr(u8)]
 pub enum LengthConfig {
     /// Fixed packet length mode. Length configured in PKTLEN register.
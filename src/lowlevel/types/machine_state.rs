@@ -1,6 +1,7 @@
 /// Radio hardware machine states.
 #[allow(non_camel_case_types)]
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[repr(u8)]
 pub enum MachineState {
     SLEEP = 0x00,
@@ -29,6 +30,8 @@ pub enum MachineState {
 }
 
 /// Machine State Error (Invalid State). Refer to the CC1101 datasheet: "10.3 SPI Read"
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum MachineStateError {
     InvalidState(u8),
 }
@@ -1,5 +1,6 @@
 /// Modulation format.
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[repr(u8)]
 pub enum ModulationFormat {
     /// 2-FSK.
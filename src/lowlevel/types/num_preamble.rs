@@ -1,5 +1,6 @@
 /// Number of preamble bytes.
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[repr(u8)]
 pub enum NumPreamble {
     // 2 preamble bytes
@@ -0,0 +1,34 @@
+/// Format of RX and TX data.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[repr(u8)]
+pub enum PacketFormat {
+    /// Normal mode, use FIFOs for RX and TX.
+    Normal = 0,
+    /// Synchronous serial mode, data in on GDO0 and data out on one of the GDO pins, clocked by
+    /// the radio.
+    SynchronousSerial = 1,
+    /// Random TX mode, sends random data using PN9 generator. Used for test. Works as normal mode
+    /// in RX.
+    RandomTx = 2,
+    /// Asynchronous serial mode, data in on GDO0 and data out on one of the GDO pins.
+    AsynchronousSerial = 3,
+}
+
+impl From<PacketFormat> for u8 {
+    fn from(value: PacketFormat) -> Self {
+        value as Self
+    }
+}
+
+impl From<u8> for PacketFormat {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => PacketFormat::Normal,
+            1 => PacketFormat::SynchronousSerial,
+            2 => PacketFormat::RandomTx,
+            3 => PacketFormat::AsynchronousSerial,
+            _ => panic!("Unknown value: {}", value),
+        }
+    }
+}
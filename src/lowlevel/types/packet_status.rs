@@ -3,6 +3,7 @@ use crate::PKTSTATUS;
 
 /// Structure representing readout of PKTSTATUS register
 #[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct PacketStatus {
     pub crc_ok: bool,
     pub carrier_sense: bool,
@@ -1,6 +1,7 @@
 /// Programs the number of times the six-bit ripple counter must expire after XOSC has stabilized before CHP_RDYn goes low.
 #[allow(non_camel_case_types)]
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[repr(u8)]
 pub enum PoTimeout {
     /// Approx. 2.3 – 2.4 μs.
@@ -18,3 +19,15 @@ impl From<PoTimeout> for u8 {
         value as Self
     }
 }
+
+impl From<u8> for PoTimeout {
+    fn from(value: u8) -> Self {
+        match value {
+            0x00 => PoTimeout::EXPIRE_COUNT_1,
+            0x01 => PoTimeout::EXPIRE_COUNT_16,
+            0x02 => PoTimeout::EXPIRE_COUNT_64,
+            0x03 => PoTimeout::EXPIRE_COUNT_256,
+            _ => panic!("Unknown value: {}", value),
+        }
+    }
+}
@@ -0,0 +1,32 @@
+/// State to enter when a packet has been received.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[repr(u8)]
+pub enum RxOffMode {
+    /// Go to IDLE.
+    Idle = 0,
+    /// Go to FSTXON, ready for a fast transition to TX.
+    FsTxOn = 1,
+    /// Stay in / go to TX.
+    Tx = 2,
+    /// Stay in / go to RX.
+    Rx = 3,
+}
+
+impl From<RxOffMode> for u8 {
+    fn from(value: RxOffMode) -> Self {
+        value as Self
+    }
+}
+
+impl From<u8> for RxOffMode {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => RxOffMode::Idle,
+            1 => RxOffMode::FsTxOn,
+            2 => RxOffMode::Tx,
+            3 => RxOffMode::Rx,
+            _ => panic!("Unknown value: {}", value),
+        }
+    }
+}
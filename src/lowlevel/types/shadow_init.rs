@@ -0,0 +1,9 @@
+/// Initial content of the shadow copy of the configuration registers.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ShadowInit {
+    /// Reset values, for a chip that has just been reset.
+    ResetValues,
+    /// Values read from the chip with a burst read.
+    ReadChip,
+}
@@ -0,0 +1,11 @@
+use crate::lowlevel::PATABLE_SIZE;
+
+/// Register contents lost in SLEEP, saved before entering it and restored after waking up.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SleepContext {
+    /// Power amplifier table.
+    pub patable: [u8; PATABLE_SIZE],
+    /// TEST2, TEST1 and TEST0.
+    pub test: [u8; 3],
+}
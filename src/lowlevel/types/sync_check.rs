@@ -1,6 +1,7 @@
 /// Sync word qualifier mode configuration.
 #[allow(non_camel_case_types)]
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[repr(u8)]
 pub enum SyncCheck {
     /// No preamble/sync.
@@ -1,5 +1,6 @@
 /// Target amplitude from channel filter.
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[repr(u8)]
 pub enum TargetAmplitude {
     /// 24 dB
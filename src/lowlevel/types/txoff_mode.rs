@@ -0,0 +1,32 @@
+/// State to enter when a packet has been sent.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[repr(u8)]
+pub enum TxOffMode {
+    /// Go to IDLE.
+    Idle = 0,
+    /// Go to FSTXON, ready for a fast transition to TX.
+    FsTxOn = 1,
+    /// Stay in / go to TX.
+    Tx = 2,
+    /// Stay in / go to RX.
+    Rx = 3,
+}
+
+impl From<TxOffMode> for u8 {
+    fn from(value: TxOffMode) -> Self {
+        value as Self
+    }
+}
+
+impl From<u8> for TxOffMode {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => TxOffMode::Idle,
+            1 => TxOffMode::FsTxOn,
+            2 => TxOffMode::Tx,
+            3 => TxOffMode::Rx,
+            _ => panic!("Unknown value: {}", value),
+        }
+    }
+}
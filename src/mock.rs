@@ -0,0 +1,341 @@
+//! Simulated CC1101 behind an SPI device, to test the driver without hardware.
+//!
+//! The chip decodes the header byte of every transaction, and keeps the configuration registers,
+//! PATABLE, FIFOs and main radio control state machine. State transitions take effect
+//! immediately, and a transmission completes as soon as STX is strobed.
+
+use crate::lowlevel::registers::{Command, Config, Status};
+use crate::{Cc1101, MachineState, FIFO_SIZE_MAX, PATABLE_SIZE};
+use core::convert::Infallible;
+use hal::spi::{ErrorType, Operation, SpiDevice};
+use std::collections::VecDeque;
+use std::vec::Vec;
+
+const READ: u8 = 0x80;
+const BURST: u8 = 0x40;
+const PATABLE: u8 = 0x3E;
+const FIFO: u8 = 0x3F;
+const NUM_STATUS: usize = Status::RCCTRL0_STATUS as usize - Status::PARTNUM as usize + 1;
+
+pub struct Chip {
+    pub config: [u8; Config::ALL.len()],
+    pub patable: [u8; PATABLE_SIZE],
+    pub state: MachineState,
+    pub rx_fifo: VecDeque<u8>,
+    pub tx_fifo: VecDeque<u8>,
+    pub rx_overflow: bool,
+    pub tx_underflow: bool,
+    pub partnum: u8,
+    pub version: u8,
+    /// Values returned by the next reads of a status register, before the simulated value.
+    pub status_reads: [VecDeque<u8>; NUM_STATUS],
+    /// Bits flipped in every write of a configuration register, e.g. to simulate SPI corruption.
+    pub flip: [u8; Config::ALL.len()],
+    /// Number of STX strobes in RX ignored by clear channel assessment.
+    pub busy: u32,
+    /// Ignore SIDLE, e.g. to simulate a wedged chip.
+    pub ignore_sidle: bool,
+    /// Transactions returning CHIP_RDYn high when waking up from SLEEP.
+    pub wake_transactions: u32,
+    /// Packets sent, the content of the TX FIFO when STX was strobed.
+    pub sent: Vec<Vec<u8>>,
+    /// Command strobes, in order.
+    pub strobes: Vec<Command>,
+    /// SFRX and SFTX strobes outside the states allowed by the datasheet.
+    pub illegal: Vec<(Command, MachineState)>,
+    /// Status bytes returned, in order.
+    pub status_bytes: Vec<u8>,
+    header: Option<u8>,
+    index: usize,
+    power_down: bool,
+    waking: u32,
+}
+
+impl Chip {
+    pub fn new() -> Self {
+        let mut config = [0; Config::ALL.len()];
+        for (value, reg) in config.iter_mut().zip(Config::ALL.iter()) {
+            *value = reg.reset_value();
+        }
+        Chip {
+            config,
+            patable: [0xC6, 0, 0, 0, 0, 0, 0, 0],
+            state: MachineState::IDLE,
+            rx_fifo: VecDeque::new(),
+            tx_fifo: VecDeque::new(),
+            rx_overflow: false,
+            tx_underflow: false,
+            partnum: 0x00,
+            version: 0x14,
+            status_reads: Default::default(),
+            flip: [0; Config::ALL.len()],
+            busy: 0,
+            ignore_sidle: false,
+            wake_transactions: 1,
+            sent: Vec::new(),
+            strobes: Vec::new(),
+            illegal: Vec::new(),
+            status_bytes: Vec::new(),
+            header: None,
+            index: 0,
+            power_down: false,
+            waking: 0,
+        }
+    }
+
+    /// Queue values returned by the next reads of `reg`.
+    pub fn queue_status(&mut self, reg: Status, values: &[u8]) {
+        self.status_reads[reg as usize - Status::PARTNUM as usize].extend(values);
+    }
+
+    /// Packet received into the RX FIFO, which overflows beyond 64 bytes.
+    pub fn receive(&mut self, data: &[u8]) {
+        self.rx_fifo.extend(data);
+        if self.rx_fifo.len() > FIFO_SIZE_MAX as usize {
+            self.rx_fifo.truncate(FIFO_SIZE_MAX as usize);
+            self.rx_overflow = true;
+            self.state = MachineState::RXFIFO_OVERFLOW;
+        }
+    }
+
+    pub fn get(&self, reg: Config) -> u8 {
+        self.config[reg as usize]
+    }
+
+    fn status_byte(&self, read: bool) -> u8 {
+        let state = match self.state {
+            MachineState::IDLE => 0b000,
+            MachineState::RX | MachineState::RX_END | MachineState::RX_RST => 0b001,
+            MachineState::TX | MachineState::TX_END => 0b010,
+            MachineState::FSTXON => 0b011,
+            MachineState::VCOON_MC
+            | MachineState::REGON_MC
+            | MachineState::MANCAL
+            | MachineState::VCOON
+            | MachineState::REGON
+            | MachineState::STARTCAL
+            | MachineState::BWBOOST
+            | MachineState::FS_LOCK
+            | MachineState::IFADCON
+            | MachineState::ENDCAL => 0b100,
+            MachineState::TXRX_SWITCH | MachineState::RXTX_SWITCH => 0b101,
+            MachineState::RXFIFO_OVERFLOW => 0b110,
+            MachineState::TXFIFO_UNDERFLOW => 0b111,
+            MachineState::SLEEP | MachineState::XOFF => 0b000,
+        };
+        let fifo = match read {
+            true => self.rx_fifo.len(),
+            false => FIFO_SIZE_MAX as usize - self.tx_fifo.len(),
+        };
+        (state << 4) | fifo.min(15) as u8
+    }
+
+    fn status_register(&mut self, addr: u8) -> u8 {
+        if let Some(value) = self.status_reads[(addr - Status::PARTNUM as u8) as usize].pop_front()
+        {
+            return value;
+        }
+        match addr {
+            a if a == Status::PARTNUM as u8 => self.partnum,
+            a if a == Status::VERSION as u8 => self.version,
+            a if a == Status::MARCSTATE as u8 => self.state as u8,
+            a if a == Status::TXBYTES as u8 => {
+                (self.tx_underflow as u8) << 7 | self.tx_fifo.len() as u8
+            }
+            a if a == Status::RXBYTES as u8 => {
+                (self.rx_overflow as u8) << 7 | self.rx_fifo.len() as u8
+            }
+            // CRC OK
+            a if a == Status::LQI as u8 => 0x80,
+            _ => 0,
+        }
+    }
+
+    fn strobe(&mut self, cmd: Command) {
+        self.strobes.push(cmd);
+        match cmd {
+            Command::SRES => {
+                let (partnum, version) = (self.partnum, self.version);
+                *self = Chip {
+                    partnum,
+                    version,
+                    strobes: core::mem::take(&mut self.strobes),
+                    status_bytes: core::mem::take(&mut self.status_bytes),
+                    ..Chip::new()
+                };
+            }
+            Command::SFSTXON => self.state = MachineState::FSTXON,
+            Command::SCAL => {
+                // Calibration result depends on the channel, to tell the captured values apart
+                self.config[Config::FSCAL3 as usize] = 0xE9;
+                self.config[Config::FSCAL2 as usize] = 0x2A;
+                self.config[Config::FSCAL1 as usize] = self.get(Config::CHANNR);
+                self.state = MachineState::IDLE;
+            }
+            Command::SRX => self.state = MachineState::RX,
+            Command::STX => {
+                if self.state == MachineState::RX && self.busy > 0 {
+                    self.busy -= 1;
+                    return;
+                }
+                if self.tx_fifo.is_empty() {
+                    self.tx_underflow = true;
+                    self.state = MachineState::TXFIFO_UNDERFLOW;
+                    return;
+                }
+                self.sent.push(self.tx_fifo.drain(..).collect());
+                // MCSM1.TXOFF_MODE
+                self.state = match self.get(Config::MCSM1) & 0b11 {
+                    0b00 => MachineState::IDLE,
+                    0b01 => MachineState::FSTXON,
+                    0b10 => MachineState::TX,
+                    _ => MachineState::RX,
+                };
+            }
+            Command::SIDLE if !self.ignore_sidle => self.state = MachineState::IDLE,
+            Command::SPWD => self.power_down = true,
+            Command::SFRX => match self.state {
+                MachineState::IDLE | MachineState::RXFIFO_OVERFLOW => {
+                    self.rx_fifo.clear();
+                    self.rx_overflow = false;
+                    self.state = MachineState::IDLE;
+                }
+                state => self.illegal.push((cmd, state)),
+            },
+            Command::SFTX => match self.state {
+                MachineState::IDLE | MachineState::TXFIFO_UNDERFLOW => {
+                    self.tx_fifo.clear();
+                    self.tx_underflow = false;
+                    self.state = MachineState::IDLE;
+                }
+                state => self.illegal.push((cmd, state)),
+            },
+            _ => {}
+        }
+    }
+
+    fn exchange(&mut self, mosi: u8) -> u8 {
+        let Some(header) = self.header else {
+            self.header = Some(mosi);
+            self.index = 0;
+            if self.state == MachineState::SLEEP {
+                // Waking up, CHIP_RDYn is still high and the transaction is ignored
+                self.waking += 1;
+                if self.waking >= self.wake_transactions {
+                    self.waking = 0;
+                    self.state = MachineState::IDLE;
+                }
+                self.header = Some(READ | BURST | Status::VCO_VC_DAC as u8);
+                self.status_bytes.push(0x80);
+                return 0x80;
+            }
+            let status = self.status_byte(mosi & READ != 0);
+            self.status_bytes.push(status);
+
+            let addr = mosi & 0x3F;
+            if (Command::SRES as u8..=Command::SNOP as u8).contains(&addr) && mosi & BURST == 0 {
+                self.strobe(command(addr));
+            }
+            return status;
+        };
+
+        let (read, burst, addr) = (header & READ != 0, header & BURST != 0, header & 0x3F);
+        let offset = self.index;
+        self.index += 1;
+        match addr {
+            a if (a as usize) < Config::ALL.len() => {
+                let reg = a as usize
+                    + if burst {
+                        offset
+                    } else {
+                        0
+                    };
+                if read {
+                    self.config[reg]
+                } else {
+                    self.config[reg] = mosi ^ self.flip[reg];
+                    0
+                }
+            }
+            PATABLE => {
+                let index = offset % PATABLE_SIZE;
+                if read {
+                    self.patable[index]
+                } else {
+                    self.patable[index] = mosi;
+                    0
+                }
+            }
+            FIFO if read => self.rx_fifo.pop_front().unwrap_or(0),
+            FIFO => {
+                self.tx_fifo.push_back(mosi);
+                0
+            }
+            a if read && burst && offset == 0 => self.status_register(a),
+            _ => 0,
+        }
+    }
+}
+
+fn command(addr: u8) -> Command {
+    [
+        Command::SRES,
+        Command::SFSTXON,
+        Command::SXOFF,
+        Command::SCAL,
+        Command::SRX,
+        Command::STX,
+        Command::SIDLE,
+        Command::SNOP,
+        Command::SWOR,
+        Command::SPWD,
+        Command::SFRX,
+        Command::SFTX,
+        Command::SWORRST,
+        Command::SNOP,
+    ][(addr - Command::SRES as u8) as usize]
+}
+
+impl ErrorType for Chip {
+    type Error = Infallible;
+}
+
+impl SpiDevice<u8> for Chip {
+    fn transaction(&mut self, operations: &mut [Operation<'_, u8>]) -> Result<(), Infallible> {
+        self.header = None;
+        for operation in operations {
+            match operation {
+                Operation::Read(buf) => buf.iter_mut().for_each(|b| *b = self.exchange(0)),
+                Operation::Write(buf) => buf.iter().for_each(|b| {
+                    self.exchange(*b);
+                }),
+                Operation::Transfer(read, write) => {
+                    for i in 0..read.len().max(write.len()) {
+                        let miso = self.exchange(write.get(i).copied().unwrap_or(0));
+                        if let Some(b) = read.get_mut(i) {
+                            *b = miso;
+                        }
+                    }
+                }
+                Operation::TransferInPlace(buf) => {
+                    buf.iter_mut().for_each(|b| *b = self.exchange(*b))
+                }
+                Operation::DelayNs(_) => {}
+            }
+        }
+        // SPWD takes effect when CSn goes high, PATABLE and TEST2..TEST0 are lost in SLEEP
+        if core::mem::take(&mut self.power_down) {
+            self.state = MachineState::SLEEP;
+            self.patable = [0; PATABLE_SIZE];
+            self.config[Config::TEST2 as usize] = 0;
+            self.config[Config::TEST1 as usize] = 0;
+            self.config[Config::TEST0 as usize] = 0;
+        }
+        Ok(())
+    }
+}
+
+/// Radio driving a simulated chip.
+pub fn radio() -> Cc1101<Chip> {
+    Cc1101::new(Chip::new()).unwrap()
+}
@@ -0,0 +1,21 @@
+//! On-off keying pulse timing, for asynchronous serial mode.
+//!
+//! In asynchronous serial mode with OOK modulation, the demodulated signal is output on a GDO pin
+//! configured as [`GdoCfg::SERIAL_DATA_OUT`](crate::GdoCfg::SERIAL_DATA_OUT). The
+//! [`PulseDetector`] turns edge timestamps captured on that pin into a [`PulseTrain`], filtering
+//! glitches and noise, and [`LineCode::decode`] turns the pulse train into [`Bits`].
+//!
+//! In the other direction, the [`OokTransmitter`] sends a pulse train, given as pulse timings or
+//! as a [`Protocol`] template, as an oversampled bitstream through the TX FIFO.
+
+mod bits;
+mod encoder;
+mod line_code;
+mod pulse;
+mod transmitter;
+
+pub use self::bits::*;
+pub use self::encoder::*;
+pub use self::line_code::*;
+pub use self::pulse::*;
+pub use self::transmitter::*;
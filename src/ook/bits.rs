@@ -0,0 +1,56 @@
+/// Decoded bit frame of at most `B` bytes, packed MSB first.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Bits<const B: usize> {
+    bytes: [u8; B],
+    len: usize,
+}
+
+impl<const B: usize> Bits<B> {
+    pub fn new() -> Self {
+        Bits {
+            bytes: [0; B],
+            len: 0,
+        }
+    }
+
+    /// Append a bit, returns `false` if the frame is full.
+    pub fn push(&mut self, bit: bool) -> bool {
+        if self.len == B * 8 {
+            return false;
+        }
+        if bit {
+            self.bytes[self.len / 8] |= 0x80 >> (self.len % 8);
+        }
+        self.len += 1;
+        true
+    }
+
+    pub fn get(&self, index: usize) -> Option<bool> {
+        if index < self.len {
+            Some(self.bytes[index / 8] & (0x80 >> (index % 8)) != 0)
+        } else {
+            None
+        }
+    }
+
+    /// Number of bits.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Bytes holding the bits, the last byte is padded with zeros.
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes[..self.len.div_ceil(8)]
+    }
+}
+
+impl<const B: usize> Default for Bits<B> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,265 @@
+use super::Pulse;
+
+/// Pulse timing of a protocol sending each bit as one pulse.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Protocol {
+    /// Pulse sent before the data bits.
+    pub sync: Option<Pulse>,
+    /// Pulse for a 0 bit.
+    pub zero: Pulse,
+    /// Pulse for a 1 bit.
+    pub one: Pulse,
+    /// Low period appended after the last pulse, e.g. before a repetition.
+    pub gap_us: u32,
+}
+
+/// First `len_bits` bits of `data`, MSB first, encoded with `protocol`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Template<'a> {
+    protocol: Protocol,
+    data: &'a [u8],
+    len_bits: usize,
+}
+
+/// Invalid pulse train description.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum EncodeError {
+    /// More bits requested than the data holds.
+    DataTooShort,
+}
+
+impl<'a> Template<'a> {
+    /// Fails when `data` holds fewer than `len_bits` bits.
+    pub fn new(protocol: Protocol, data: &'a [u8], len_bits: usize) -> Result<Self, EncodeError> {
+        if len_bits > data.len().saturating_mul(8) {
+            return Err(EncodeError::DataTooShort);
+        }
+        Ok(Template {
+            protocol,
+            data,
+            len_bits,
+        })
+    }
+}
+
+/// Description of a pulse train to be sent.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum PulseSource<'a> {
+    /// Explicit pulse timings.
+    Pulses(&'a [Pulse]),
+    /// Data bits encoded with a protocol.
+    Template(Template<'a>),
+}
+
+impl PulseSource<'_> {
+    /// Number of pulses.
+    pub fn len(&self) -> usize {
+        match self {
+            PulseSource::Pulses(pulses) => pulses.len(),
+            PulseSource::Template(template) => {
+                template.protocol.sync.is_some() as usize + template.len_bits
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Pulse at `index`, which must be less than [`PulseSource::len`].
+    pub fn pulse(&self, index: usize) -> Pulse {
+        match self {
+            PulseSource::Pulses(pulses) => pulses[index],
+            PulseSource::Template(Template {
+                protocol,
+                data,
+                len_bits,
+            }) => {
+                let bit_index = match protocol.sync {
+                    Some(sync) if index == 0 => return sync,
+                    Some(_) => index - 1,
+                    None => index,
+                };
+                let mut pulse = match data[bit_index / 8] & (0x80 >> (bit_index % 8)) != 0 {
+                    true => protocol.one,
+                    false => protocol.zero,
+                };
+                if bit_index + 1 == *len_bits {
+                    pulse.low_us = pulse.low_us.saturating_add(protocol.gap_us);
+                }
+                pulse
+            }
+        }
+    }
+
+    /// Duration of all pulses, in microseconds.
+    pub fn duration_us(&self) -> u64 {
+        (0..self.len())
+            .map(|i| {
+                let pulse = self.pulse(i);
+                pulse.high_us as u64 + pulse.low_us as u64
+            })
+            .sum()
+    }
+}
+
+/// Converts a pulse train into a bitstream oversampled at one bit per `sample_us`, packed MSB
+/// first. Period boundaries are rounded to the nearest sample without accumulating errors.
+pub struct BitstreamEncoder<'a> {
+    source: PulseSource<'a>,
+    sample_us: u64,
+    repeats: u16,
+    total_samples: u64,
+    position: u64,
+    segment: usize,
+    segment_end_us: u64,
+    segment_end: u64,
+    level: bool,
+}
+
+impl<'a> BitstreamEncoder<'a> {
+    /// Encode `source` sent `repeats` times back to back.
+    pub fn new(source: PulseSource<'a>, sample_us: u32, repeats: u16) -> Self {
+        let sample_us = sample_us.max(1) as u64;
+        let total_us = source.duration_us() * repeats as u64;
+        BitstreamEncoder {
+            source,
+            sample_us,
+            repeats,
+            total_samples: (total_us + sample_us / 2) / sample_us,
+            position: 0,
+            segment: 0,
+            segment_end_us: 0,
+            segment_end: 0,
+            level: false,
+        }
+    }
+
+    /// Total length of the bitstream in bytes, the last byte is padded with zeros.
+    pub fn len_bytes(&self) -> usize {
+        self.total_samples.div_ceil(8) as usize
+    }
+
+    /// Bytes not yet returned.
+    pub fn remaining_bytes(&self) -> usize {
+        self.len_bytes() - self.position.div_ceil(8) as usize
+    }
+
+    fn next_sample(&mut self) -> Option<bool> {
+        if self.position >= self.total_samples {
+            return None;
+        }
+        // Each pulse is two segments, high and low, skipping those rounded to zero samples
+        let segments = 2 * self.source.len() * self.repeats as usize;
+        while self.position >= self.segment_end && self.segment < segments {
+            let pulse = self.source.pulse((self.segment / 2) % self.source.len());
+            self.level = self.segment.is_multiple_of(2);
+            self.segment_end_us += match self.level {
+                true => pulse.high_us as u64,
+                false => pulse.low_us as u64,
+            };
+            self.segment_end = (self.segment_end_us + self.sample_us / 2) / self.sample_us;
+            self.segment += 1;
+        }
+        self.position += 1;
+        Some(self.level)
+    }
+
+    /// Next byte of the bitstream.
+    pub fn next_byte(&mut self) -> Option<u8> {
+        if self.position >= self.total_samples {
+            return None;
+        }
+        let mut byte = 0;
+        for bit in 0..8 {
+            if self.next_sample().unwrap_or(false) {
+                byte |= 0x80 >> bit;
+            }
+        }
+        Some(byte)
+    }
+
+    /// Fill `buf` with the next bytes of the bitstream, returns the number of bytes written.
+    pub fn fill(&mut self, buf: &mut [u8]) -> usize {
+        let mut n = 0;
+        for byte in buf.iter_mut() {
+            match self.next_byte() {
+                Some(b) => *byte = b,
+                None => break,
+            }
+            n += 1;
+        }
+        n
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(source: PulseSource, sample_us: u32, repeats: u16) -> ([u8; 8], usize) {
+        let mut encoder = BitstreamEncoder::new(source, sample_us, repeats);
+        let len = encoder.len_bytes();
+        let mut bytes = [0; 8];
+        assert_eq!(encoder.fill(&mut bytes), len);
+        assert_eq!(encoder.remaining_bytes(), 0);
+        (bytes, len)
+    }
+
+    #[test]
+    fn test_pulses() {
+        let pulses = [
+            Pulse {
+                high_us: 300,
+                low_us: 600,
+            },
+            Pulse {
+                high_us: 200,
+                low_us: 100,
+            },
+        ];
+        let (bytes, len) = encode(PulseSource::Pulses(&pulses), 100, 1);
+        assert_eq!((bytes[..len].as_ref(), len), (&[0b1110_0000, 0b0110_0000][..], 2));
+    }
+
+    #[test]
+    fn test_rounding_does_not_accumulate() {
+        let pulses = [Pulse {
+            high_us: 150,
+            low_us: 250,
+        }];
+        // Edges at 1.5, 4, 5.5, 8, 9.5 and 12 samples
+        let (bytes, len) = encode(PulseSource::Pulses(&pulses), 100, 3);
+        assert_eq!((bytes[..len].as_ref(), len), (&[0b1100_1100, 0b1100_0000][..], 2));
+    }
+
+    #[test]
+    fn test_template() {
+        let protocol = Protocol {
+            sync: Some(Pulse {
+                high_us: 100,
+                low_us: 700,
+            }),
+            zero: Pulse {
+                high_us: 100,
+                low_us: 300,
+            },
+            one: Pulse {
+                high_us: 300,
+                low_us: 100,
+            },
+            gap_us: 800,
+        };
+        assert_eq!(Template::new(protocol, &[0b1000_0000], 9), Err(EncodeError::DataTooShort));
+        let source = PulseSource::Template(Template::new(protocol, &[0b1000_0000], 2).unwrap());
+        assert_eq!(source.len(), 3);
+        assert_eq!(source.duration_us(), 800 + 400 + 400 + 800);
+
+        let (bytes, len) = encode(source, 100, 2);
+        assert_eq!((bytes[..len].as_ref(), len), (&[0x80, 0xE8, 0x00, 0x80, 0xE8, 0x00][..], 6));
+    }
+}
@@ -0,0 +1,268 @@
+use super::{Bits, Pulse};
+use core::iter::repeat_n;
+
+/// Mapping of pulses to bits.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum LineCode {
+    /// Pulse width modulation, one pulse per bit: a short high period is 0, a long one is 1.
+    Pwm {
+        short_us: u32,
+        long_us: u32,
+    },
+    /// Pulse position modulation, one bit per low period between two pulses: a short low period
+    /// is 0, a long one is 1.
+    Ppm {
+        short_us: u32,
+        long_us: u32,
+    },
+    /// Manchester code, G.E. Thomas convention: high then low is 1, low then high is 0.
+    Manchester {
+        half_bit_us: u32,
+    },
+    /// Non-return-to-zero, high is 1, low is 0 for each bit period.
+    Nrz {
+        bit_us: u32,
+    },
+}
+
+/// Pulse train not matching the line code.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum DecodeError {
+    /// Pulse at the given index has a period that is not valid for the line code.
+    InvalidPulse(usize),
+    /// More bits than fit into the frame.
+    Overflow,
+}
+
+impl LineCode {
+    /// Decode a pulse train into bits. Periods may deviate from the nominal ones by `tolerance_us`
+    /// per bit period. The low period of the last pulse is the gap ending the frame, and is only
+    /// used where needed to complete the last bit.
+    pub fn decode<const B: usize>(
+        &self,
+        pulses: &[Pulse],
+        tolerance_us: u32,
+    ) -> Result<Bits<B>, DecodeError> {
+        let mut bits = Bits::new();
+
+        match *self {
+            LineCode::Pwm {
+                short_us,
+                long_us,
+            } => {
+                for (i, pulse) in pulses.iter().enumerate() {
+                    let bit = classify(pulse.high_us, short_us, long_us, tolerance_us)
+                        .ok_or(DecodeError::InvalidPulse(i))?;
+                    push(&mut bits, bit)?;
+                }
+            }
+            LineCode::Ppm {
+                short_us,
+                long_us,
+            } => {
+                let gaps = pulses.len().saturating_sub(1);
+                for (i, pulse) in pulses[..gaps].iter().enumerate() {
+                    let bit = classify(pulse.low_us, short_us, long_us, tolerance_us)
+                        .ok_or(DecodeError::InvalidPulse(i))?;
+                    push(&mut bits, bit)?;
+                }
+            }
+            LineCode::Manchester {
+                half_bit_us,
+            } => {
+                // A frame starting with 0 begins with a low half bit, which is not seen
+                bits = match manchester(pulses, half_bit_us, tolerance_us, false) {
+                    Err(DecodeError::InvalidPulse(_)) => {
+                        manchester(pulses, half_bit_us, tolerance_us, true)?
+                    }
+                    result => result?,
+                };
+            }
+            LineCode::Nrz {
+                bit_us,
+            } => {
+                for (i, pulse) in pulses.iter().enumerate() {
+                    let last = i + 1 == pulses.len();
+                    let ones = periods(pulse.high_us, bit_us, tolerance_us)
+                        .ok_or(DecodeError::InvalidPulse(i))?;
+                    let zeros = match last {
+                        true => 0,
+                        false => periods(pulse.low_us, bit_us, tolerance_us)
+                            .ok_or(DecodeError::InvalidPulse(i))?,
+                    };
+                    for bit in repeat_n(true, ones).chain(repeat_n(false, zeros)) {
+                        push(&mut bits, bit)?;
+                    }
+                }
+            }
+        }
+        Ok(bits)
+    }
+}
+
+fn push<const B: usize>(bits: &mut Bits<B>, bit: bool) -> Result<(), DecodeError> {
+    if bits.push(bit) {
+        Ok(())
+    } else {
+        Err(DecodeError::Overflow)
+    }
+}
+
+/// Classify a period as short (false) or long (true).
+fn classify(duration_us: u32, short_us: u32, long_us: u32, tolerance_us: u32) -> Option<bool> {
+    if duration_us.abs_diff(short_us) <= tolerance_us {
+        Some(false)
+    } else if duration_us.abs_diff(long_us) <= tolerance_us {
+        Some(true)
+    } else {
+        None
+    }
+}
+
+/// Number of whole periods in a duration, at least one. A zero period never matches.
+fn periods(duration_us: u32, period_us: u32, tolerance_us: u32) -> Option<usize> {
+    if period_us == 0 {
+        return None;
+    }
+    let n = (duration_us.saturating_add(period_us / 2) / period_us).max(1);
+    let nominal_us = n.checked_mul(period_us)?;
+    if duration_us.abs_diff(nominal_us) <= n.saturating_mul(tolerance_us) {
+        Some(n as usize)
+    } else {
+        None
+    }
+}
+
+fn manchester<const B: usize>(
+    pulses: &[Pulse],
+    half_bit_us: u32,
+    tolerance_us: u32,
+    leading_low: bool,
+) -> Result<Bits<B>, DecodeError> {
+    let mut bits = Bits::new();
+    let mut first_half = leading_low.then_some(false);
+
+    for (i, pulse) in pulses.iter().enumerate() {
+        let last = i + 1 == pulses.len();
+        let high = periods(pulse.high_us, half_bit_us, tolerance_us)
+            .filter(|&n| n <= 2)
+            .ok_or(DecodeError::InvalidPulse(i))?;
+        let low = match last {
+            true => 1,
+            false => periods(pulse.low_us, half_bit_us, tolerance_us)
+                .filter(|&n| n <= 2)
+                .ok_or(DecodeError::InvalidPulse(i))?,
+        };
+
+        for level in repeat_n(true, high).chain(repeat_n(false, low)) {
+            match first_half.take() {
+                None => first_half = Some(level),
+                Some(first) if first != level => push(&mut bits, first)?,
+                Some(_) => return Err(DecodeError::InvalidPulse(i)),
+            }
+        }
+    }
+    // An unpaired low half bit left over is part of the gap
+    Ok(bits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GAP: u32 = 10_000;
+
+    fn pulses<const N: usize>(periods: [(u32, u32); N]) -> [Pulse; N] {
+        periods.map(|(high_us, low_us)| Pulse {
+            high_us,
+            low_us,
+        })
+    }
+
+    fn bits(bits: &Bits<4>) -> ([u8; 4], usize) {
+        let mut bytes = [0; 4];
+        bytes[..bits.bytes().len()].copy_from_slice(bits.bytes());
+        (bytes, bits.len())
+    }
+
+    #[test]
+    fn test_pwm() {
+        let code = LineCode::Pwm {
+            short_us: 300,
+            long_us: 900,
+        };
+        let train = pulses([(310, 900), (880, 300), (920, 310), (290, GAP)]);
+        let decoded = code.decode::<4>(&train, 100).unwrap();
+        assert_eq!(bits(&decoded), ([0b0110_0000, 0, 0, 0], 4));
+
+        let train = pulses([(310, 900), (600, GAP)]);
+        assert_eq!(code.decode::<4>(&train, 100), Err(DecodeError::InvalidPulse(1)));
+    }
+
+    #[test]
+    fn test_ppm() {
+        let code = LineCode::Ppm {
+            short_us: 1000,
+            long_us: 2000,
+        };
+        let train = pulses([(500, 2000), (500, 1000), (500, 2050), (500, GAP)]);
+        let decoded = code.decode::<4>(&train, 200).unwrap();
+        assert_eq!(bits(&decoded), ([0b1010_0000, 0, 0, 0], 3));
+    }
+
+    #[test]
+    fn test_manchester() {
+        let code = LineCode::Manchester {
+            half_bit_us: 500,
+        };
+        // 1 0 0 1 1
+        let train = pulses([(500, 1000), (500, 500), (1000, 500), (500, GAP)]);
+        let decoded = code.decode::<4>(&train, 100).unwrap();
+        assert_eq!(bits(&decoded), ([0b1001_1000, 0, 0, 0], 5));
+
+        // 0 1, starting with an unseen low half bit
+        let train = pulses([(1000, GAP)]);
+        let decoded = code.decode::<4>(&train, 100).unwrap();
+        assert_eq!(bits(&decoded), ([0b0100_0000, 0, 0, 0], 2));
+    }
+
+    #[test]
+    fn test_nrz() {
+        let code = LineCode::Nrz {
+            bit_us: 100,
+        };
+        let train = pulses([(205, 95), (100, 190), (110, GAP)]);
+        let decoded = code.decode::<4>(&train, 20).unwrap();
+        assert_eq!(bits(&decoded), ([0b1101_0010, 0, 0, 0], 7));
+    }
+
+    #[test]
+    fn test_overflow() {
+        let code = LineCode::Nrz {
+            bit_us: 100,
+        };
+        let train = pulses([(100, 100), (3100, GAP)]);
+        assert_eq!(code.decode::<4>(&train, 20), Err(DecodeError::Overflow));
+    }
+
+    #[test]
+    fn test_invalid_periods() {
+        let train = pulses([(100, 100), (u32::MAX, GAP)]);
+        let code = LineCode::Nrz {
+            bit_us: 0,
+        };
+        assert_eq!(code.decode::<4>(&train, 20), Err(DecodeError::InvalidPulse(0)));
+        let code = LineCode::Manchester {
+            half_bit_us: 0,
+        };
+        assert_eq!(code.decode::<4>(&train, 20), Err(DecodeError::InvalidPulse(0)));
+
+        // Long pulses must not overflow the nominal duration
+        let code = LineCode::Nrz {
+            bit_us: 100,
+        };
+        assert_eq!(code.decode::<4>(&train, u32::MAX), Err(DecodeError::Overflow));
+    }
+}
@@ -0,0 +1,309 @@
+/// Carrier on (high) followed by carrier off (low) period, in microseconds.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Pulse {
+    pub high_us: u32,
+    pub low_us: u32,
+}
+
+/// Pulse classification limits.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PulseConfig {
+    /// Level changes lasting less than this are glitches, and are removed.
+    pub glitch_us: u32,
+    /// High periods shorter than this are noise, and discard the frame received so far.
+    pub noise_floor_us: u32,
+    /// Low period ending a frame.
+    pub gap_us: u32,
+}
+
+/// Pulses of a frame, at most `N`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PulseTrain<const N: usize> {
+    pulses: [Pulse; N],
+    len: usize,
+    truncated: bool,
+}
+
+impl<const N: usize> PulseTrain<N> {
+    pub fn new() -> Self {
+        PulseTrain {
+            pulses: [Pulse::default(); N],
+            len: 0,
+            truncated: false,
+        }
+    }
+
+    fn push(&mut self, pulse: Pulse) {
+        if self.len < N {
+            self.pulses[self.len] = pulse;
+            self.len += 1;
+        } else {
+            self.truncated = true;
+        }
+    }
+
+    fn clear(&mut self) {
+        self.len = 0;
+        self.truncated = false;
+    }
+
+    pub fn pulses(&self) -> &[Pulse] {
+        &self.pulses[..self.len]
+    }
+
+    /// More than `N` pulses were received, the remainder was dropped.
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+}
+
+impl<const N: usize> Default for PulseTrain<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Collects edges of the demodulated signal into pulse trains.
+///
+/// An edge is only accepted once the level after it lasted for the glitch time, so the latest edge
+/// is pending until the next one arrives, or until [`PulseDetector::timeout`] is called.
+pub struct PulseDetector<const N: usize> {
+    config: PulseConfig,
+    level: bool,
+    level_start_us: u32,
+    pending_us: Option<u32>,
+    high_us: Option<u32>,
+    train: PulseTrain<N>,
+}
+
+impl<const N: usize> PulseDetector<N> {
+    pub fn new(config: PulseConfig) -> Self {
+        PulseDetector {
+            config,
+            level: false,
+            level_start_us: 0,
+            pending_us: None,
+            high_us: None,
+            train: PulseTrain::new(),
+        }
+    }
+
+    /// Feed an edge at `time_us`, `level` being the level after the edge. Returns a complete
+    /// frame, once a low period reaches the gap time.
+    pub fn feed(&mut self, time_us: u32, level: bool) -> Option<PulseTrain<N>> {
+        let frame = match self.pending_us {
+            Some(pending_us) if time_us.wrapping_sub(pending_us) < self.config.glitch_us => {
+                // Back to the previous level, the pending edge was a glitch
+                self.pending_us = None;
+                None
+            }
+            Some(pending_us) => {
+                let frame = self.accept(pending_us);
+                self.pending_us = (level != self.level).then_some(time_us);
+                frame
+            }
+            None if level != self.level => {
+                self.pending_us = Some(time_us);
+                None
+            }
+            None => None,
+        };
+        if frame.is_some() || self.level {
+            return frame;
+        }
+
+        // The low level lasted at least until this edge, whether or not the edge is a glitch
+        let low_us = time_us.wrapping_sub(self.level_start_us);
+        if low_us >= self.config.gap_us {
+            self.finish(low_us)
+        } else {
+            None
+        }
+    }
+
+    /// Check for the end of a frame at `now_us`, when no edge has been received for a while.
+    pub fn timeout(&mut self, now_us: u32) -> Option<PulseTrain<N>> {
+        let mut frame = None;
+        if let Some(pending_us) = self.pending_us {
+            if now_us.wrapping_sub(pending_us) >= self.config.glitch_us {
+                self.pending_us = None;
+                frame = self.accept(pending_us);
+            }
+        }
+        if frame.is_none() && !self.level && self.pending_us.is_none() {
+            let low_us = now_us.wrapping_sub(self.level_start_us);
+            if low_us >= self.config.gap_us {
+                frame = self.finish(low_us);
+            }
+        }
+        frame
+    }
+
+    /// Accept the edge at `time_us`, ending the current level.
+    fn accept(&mut self, time_us: u32) -> Option<PulseTrain<N>> {
+        let duration_us = time_us.wrapping_sub(self.level_start_us);
+        let ended = self.level;
+        self.level = !self.level;
+        self.level_start_us = time_us;
+
+        if ended {
+            if duration_us < self.config.noise_floor_us {
+                self.high_us = None;
+                self.train.clear();
+            } else {
+                self.high_us = Some(duration_us);
+            }
+            None
+        } else if duration_us >= self.config.gap_us {
+            self.finish(duration_us)
+        } else {
+            if let Some(high_us) = self.high_us.take() {
+                self.train.push(Pulse {
+                    high_us,
+                    low_us: duration_us,
+                });
+            }
+            None
+        }
+    }
+
+    fn finish(&mut self, low_us: u32) -> Option<PulseTrain<N>> {
+        if let Some(high_us) = self.high_us.take() {
+            self.train.push(Pulse {
+                high_us,
+                low_us,
+            });
+        }
+        if self.train.pulses().is_empty() {
+            return None;
+        }
+        let frame = self.train;
+        self.train.clear();
+        // Only report the gap once
+        self.level_start_us = self.level_start_us.wrapping_add(low_us);
+        Some(frame)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: PulseConfig = PulseConfig {
+        glitch_us: 50,
+        noise_floor_us: 100,
+        gap_us: 5000,
+    };
+
+    fn feed_all<const N: usize>(
+        detector: &mut PulseDetector<N>,
+        edges: &[(u32, bool)],
+    ) -> Option<PulseTrain<N>> {
+        let mut frame = None;
+        for &(time_us, level) in edges {
+            if let Some(train) = detector.feed(time_us, level) {
+                frame = Some(train);
+            }
+        }
+        frame
+    }
+
+    #[test]
+    fn test_frame_ends_at_gap() {
+        let mut detector = PulseDetector::<8>::new(CONFIG);
+        let frame = feed_all(
+            &mut detector,
+            &[
+                (1000, true),
+                (1300, false),
+                (1900, true),
+                (2500, false),
+                (2800, true),
+                (3100, false),
+            ],
+        );
+        assert_eq!(frame, None);
+        assert_eq!(detector.timeout(3120), None);
+
+        let frame = detector.timeout(3100 + 5000).unwrap();
+        assert_eq!(
+            frame.pulses(),
+            &[
+                Pulse {
+                    high_us: 300,
+                    low_us: 600
+                },
+                Pulse {
+                    high_us: 600,
+                    low_us: 300
+                },
+                Pulse {
+                    high_us: 300,
+                    low_us: 5000
+                },
+            ]
+        );
+        assert_eq!(detector.timeout(3100 + 5001), None);
+    }
+
+    #[test]
+    fn test_glitches_are_removed() {
+        let mut detector = PulseDetector::<8>::new(CONFIG);
+        let frame = feed_all(
+            &mut detector,
+            &[
+                (1000, true),
+                (1100, false),
+                (1110, true),
+                (1300, false),
+                (1500, true),
+                (1510, false),
+                (1900, true),
+                (2200, false),
+                (8000, true),
+            ],
+        )
+        .unwrap();
+        assert_eq!(
+            frame.pulses(),
+            &[
+                Pulse {
+                    high_us: 300,
+                    low_us: 600
+                },
+                Pulse {
+                    high_us: 300,
+                    low_us: 5800
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_noise_discards_frame() {
+        let mut detector = PulseDetector::<8>::new(CONFIG);
+        let frame = feed_all(
+            &mut detector,
+            &[
+                (1000, true),
+                (1300, false),
+                (1600, true),
+                (1670, false),
+                (2000, true),
+                (2300, false),
+                (9000, true),
+            ],
+        )
+        .unwrap();
+        assert_eq!(
+            frame.pulses(),
+            &[Pulse {
+                high_us: 300,
+                low_us: 6700
+            }]
+        );
+    }
+}
@@ -0,0 +1,172 @@
+use super::{BitstreamEncoder, PulseSource};
+use crate::lowlevel::{access::Access, registers::*};
+use crate::{
+    Cc1101, Error, MachineState, ModulationFormat, PacketFormat, PacketLength, SyncMode, UserError,
+    FIFO_SIZE_MAX,
+};
+use hal::spi::SpiDevice;
+
+/// Data rates (in bits per second) supported with OOK, from the datasheet electrical
+/// specifications.
+const MIN_BAUD: u32 = 600;
+const MAX_BAUD: u32 = 250_000;
+
+/// Sends pulse trains with OOK modulation, as a bitstream oversampled at one bit per sample
+/// period. A 1 bit turns the carrier on with PATABLE\[1\], a 0 bit turns it off with PATABLE\[0\].
+///
+/// Preamble, sync word, CRC, whitening, FEC and Manchester encoding are disabled, so the FIFO
+/// contents are sent as they are. Bitstreams longer than 255 bytes are sent in infinite packet
+/// length mode, switching to fixed packet length for the last bytes.
+pub struct OokTransmitter {
+    sample_us: u32,
+}
+
+impl OokTransmitter {
+    /// Configure the radio for OOK transmission with a sample period of `sample_us`, and the
+    /// carrier on with PA setting `pa_power`. Fails with `UserError::InvalidSamplePeriod` when
+    /// the sample period is outside 4 to 1666 µs, the 0.6 to 250 kBaud supported with OOK.
+    pub fn configure<SPI, SpiE, O>(
+        radio: &mut Cc1101<SPI, O>,
+        sample_us: u32,
+        pa_power: u8,
+    ) -> Result<Self, Error<SpiE>>
+    where
+        SPI: SpiDevice<u8, Error = SpiE>,
+        O: FnMut(StatusByte),
+    {
+        if sample_us == 0 || !(MIN_BAUD..=MAX_BAUD).contains(&(1_000_000 / sample_us)) {
+            return Err(Error::UserInputError(UserError::InvalidSamplePeriod(sample_us)));
+        }
+        radio.set_modulation_format(ModulationFormat::AmplitudeShiftOnOffKeying)?;
+        radio.set_data_rate(1_000_000 / sample_us as u64)?;
+        radio.set_sync_mode(SyncMode::Disabled)?;
+        radio.crc_enable(false)?;
+        radio.white_data_enable(false)?;
+        radio.fec_enable(false)?;
+        radio.manchester_enable(false)?;
+        radio.set_packet_format(PacketFormat::Normal)?;
+
+        // OOK uses PATABLE[0] for the carrier off and PATABLE[1] for the carrier on
+        radio.0.modify_register(Config::FREND0, |r| FREND0(r).modify().pa_power(1).bits())?;
+        radio.0.access_patable(Access::Write, &mut [0x00, pa_power])?;
+
+        Ok(OokTransmitter {
+            sample_us,
+        })
+    }
+
+    /// Sample period in microseconds.
+    pub fn sample_us(&self) -> u32 {
+        self.sample_us
+    }
+
+    /// Send `source` `repeats` times back to back, waiting until it has been transmitted.
+    /// A TX FIFO underflow and the poll limit are handled as in [`Cc1101::transmit`]. The radio
+    /// is brought to IDLE first, so clear channel assessment does not apply.
+    pub fn send<SPI, SpiE, O>(
+        &self,
+        radio: &mut Cc1101<SPI, O>,
+        source: PulseSource,
+        repeats: u16,
+    ) -> Result<(), Error<SpiE>>
+    where
+        SPI: SpiDevice<u8, Error = SpiE>,
+        O: FnMut(StatusByte),
+    {
+        let mut encoder = BitstreamEncoder::new(source, self.sample_us, repeats);
+        if encoder.len_bytes() == 0 {
+            return Ok(());
+        }
+        // The packet byte counter wraps at 256, so the length must not be a multiple of it
+        let mut padding = encoder.len_bytes().is_multiple_of(256) as usize;
+        let total = encoder.len_bytes() + padding;
+        let last_lap = (total % 256) as u8;
+
+        radio.exit_rx_tx()?;
+        radio.await_machine_state(MachineState::IDLE)?;
+        radio.flush_tx_fifo_buffer()?;
+        let mut infinite = total > u8::MAX as usize;
+        match infinite {
+            true => radio.set_packet_length(PacketLength::Infinite)?,
+            false => radio.set_packet_length(PacketLength::Fixed(total as u8))?,
+        }
+
+        let mut buf = [0; FIFO_SIZE_MAX as usize];
+        let mut written = 0;
+        let mut in_fifo = 0;
+        let mut started = false;
+        let mut polls = 0;
+        let txoff_mode = radio.get_txoff_mode()?;
+        loop {
+            let free = FIFO_SIZE_MAX as usize - in_fifo;
+            let mut n = encoder.fill(&mut buf[..free]);
+            while n < free && padding > 0 {
+                buf[n] = 0;
+                n += 1;
+                padding -= 1;
+            }
+            if n > 0 {
+                radio.0.access_fifo(Access::Write, &mut [], &mut buf[..n])?;
+                written += n;
+                polls = 0;
+            } else if polls == radio.0.poll_limit {
+                radio.abort_transmission()?;
+                return Err(Error::Timeout);
+            } else {
+                polls += 1;
+            }
+            if !started {
+                radio.enable_tx()?;
+                started = true;
+            }
+
+            in_fifo = match radio.get_tx_bytes() {
+                Ok(bytes) => bytes as usize,
+                Err(Error::TxUnderflow) => return radio.recover_tx_underflow(txoff_mode),
+                Err(e) => return Err(e),
+            };
+            // Leave infinite mode once the remaining bytes are less than one counter wrap
+            if infinite && total - (written - in_fifo) < 256 {
+                radio.set_packet_length(PacketLength::Fixed(last_lap))?;
+                radio.flush_registers()?;
+                infinite = false;
+            }
+            if written == total {
+                break;
+            }
+        }
+
+        radio.await_transmission()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lowlevel::convert::from_drate;
+    use crate::mock::radio;
+
+    #[test]
+    fn test_sample_period() {
+        let mut radio = radio();
+        let reset = radio.0.spi.config;
+        for sample_us in [0, 3, 1667, u32::MAX] {
+            assert_eq!(
+                OokTransmitter::configure(&mut radio, sample_us, 0xC0).err(),
+                Some(Error::UserInputError(UserError::InvalidSamplePeriod(sample_us)))
+            );
+        }
+        // Nothing configured
+        assert_eq!(radio.0.spi.config, reset);
+
+        for (sample_us, baud) in [(4, 250_000), (100, 10_000), (1666, 600)] {
+            let ook = OokTransmitter::configure(&mut radio, sample_us, 0xC0).unwrap();
+            assert_eq!(ook.sample_us(), sample_us);
+            let (mantissa, exponent) = from_drate(baud);
+            let chip = &radio.0.spi;
+            assert_eq!(chip.get(Config::MDMCFG4) & 0x0F, exponent);
+            assert_eq!(chip.get(Config::MDMCFG3), mantissa);
+            assert_eq!(chip.patable[..2], [0x00, 0xC0]);
+        }
+    }
+}
@@ -0,0 +1,235 @@
+//! Implementations of the [`radio`] crate traits, enabled with the `radio` feature.
+//!
+//! Packets passed to [`Transmit`] and returned by [`Receive`] start with the address byte when
+//! address filtering is enabled, followed by the payload. The length byte of variable length
+//! packets is added and removed by the driver. Packets must fit the FIFO.
+//!
+//! [`Receive::check_receive`] detects a complete packet by the radio leaving RX, so
+//! MCSM1.RXOFF_MODE must not be RX.
+
+use crate::lowlevel::{access::Access, convert::from_rssi_to_rssi_dbm, registers::*};
+use crate::{Cc1101, Error, MachineState, RadioMode, UserError, FIFO_SIZE_MAX};
+use ::radio::{BasicInfo, Busy, Channel, Receive, Rssi, State, Transmit};
+use core::fmt::Debug;
+use hal::spi::SpiDevice;
+
+/// Channel selection for [`Channel::set_channel`].
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum RadioChannel {
+    /// Channel number written to CHANNR, relative to the base frequency.
+    Number(u8),
+    /// Base frequency in Hz.
+    Frequency(u64),
+}
+
+impl ::radio::RadioState for RadioMode {
+    fn idle() -> Self {
+        RadioMode::Idle
+    }
+
+    fn sleep() -> Self {
+        RadioMode::Sleep
+    }
+}
+
+impl From<MachineState> for RadioMode {
+    fn from(state: MachineState) -> Self {
+        match state {
+            MachineState::SLEEP => RadioMode::Sleep,
+            // The frequency synthesizer is on in FSTXON, but neither RX nor TX
+            MachineState::IDLE | MachineState::XOFF | MachineState::FSTXON => RadioMode::Idle,
+            MachineState::RX
+            | MachineState::RX_END
+            | MachineState::RX_RST
+            | MachineState::TXRX_SWITCH
+            | MachineState::RXFIFO_OVERFLOW => RadioMode::Receive,
+            MachineState::TX
+            | MachineState::TX_END
+            | MachineState::RXTX_SWITCH
+            | MachineState::TXFIFO_UNDERFLOW => RadioMode::Transmit,
+            _ => RadioMode::Calibrate,
+        }
+    }
+}
+
+impl<SPI, SpiE, O> Transmit for Cc1101<SPI, O>
+where
+    SPI: SpiDevice<u8, Error = SpiE>,
+    O: FnMut(StatusByte),
+    SpiE: Debug,
+{
+    type Error = Error<SpiE>;
+
+    fn start_transmit(&mut self, data: &[u8]) -> Result<(), Self::Error> {
+        let header = self.0.length_field as usize;
+        if data.len() > FIFO_SIZE_MAX as usize - header {
+            return Err(Error::UserInputError(UserError::ArrayTooLong(data.len())));
+        }
+        if self.0.address_field && data.is_empty() {
+            return Err(Error::UserInputError(UserError::MissingAddressParameter));
+        }
+
+        self.exit_rx_tx()?;
+        self.await_machine_state(MachineState::IDLE)?;
+        self.flush_tx_fifo_buffer()?;
+
+        let mut packet = [0; FIFO_SIZE_MAX as usize];
+        packet[0] = data.len() as u8;
+        packet[header..header + data.len()].copy_from_slice(data);
+        self.0.access_fifo(Access::Write, &mut [], &mut packet[..header + data.len()])?;
+        self.enable_tx()
+    }
+
+    fn check_transmit(&mut self) -> Result<bool, Self::Error> {
+        match self.get_machine_state()? {
+            MachineState::TXFIFO_UNDERFLOW => {
+                let txoff_mode = self.get_txoff_mode()?;
+                self.recover_tx_underflow(txoff_mode).map(|_| true)
+            }
+            // STX is ignored in RX when clear channel assessment reports the channel busy
+            MachineState::RX if self.get_tx_bytes()? > 0 => {
+                self.abort_busy_transmission().map(|_| true)
+            }
+            MachineState::IDLE | MachineState::FSTXON | MachineState::RX => {
+                Ok(self.get_tx_bytes()? == 0)
+            }
+            _ => Ok(false),
+        }
+    }
+}
+
+impl<SPI, SpiE, O> Receive for Cc1101<SPI, O>
+where
+    SPI: SpiDevice<u8, Error = SpiE>,
+    O: FnMut(StatusByte),
+    SpiE: Debug,
+{
+    type Error = Error<SpiE>;
+    type Info = BasicInfo;
+
+    fn start_receive(&mut self) -> Result<(), Self::Error> {
+        self.exit_rx_tx()?;
+        self.await_machine_state(MachineState::IDLE)?;
+        self.flush_rx_fifo_buffer()?;
+        self.enable_rx()
+    }
+
+    fn check_receive(&mut self, restart: bool) -> Result<bool, Self::Error> {
+        match self.get_machine_state()? {
+            MachineState::RXFIFO_OVERFLOW if restart => {
+                self.flush_rx_fifo_buffer()?;
+                self.enable_rx()?;
+                Ok(false)
+            }
+            MachineState::RXFIFO_OVERFLOW => Err(Error::RxOverflow),
+            MachineState::IDLE | MachineState::FSTXON | MachineState::TX => {
+                match self.get_rx_bytes()? {
+                    0 if restart => self.enable_rx().map(|_| false),
+                    0 => Ok(false),
+                    _ => Ok(true),
+                }
+            }
+            _ => Ok(false),
+        }
+    }
+
+    fn get_received(&mut self, buff: &mut [u8]) -> Result<(usize, Self::Info), Self::Error> {
+        let len = match self.0.length_field {
+            true => {
+                let mut length = [0];
+                self.0.access_fifo(Access::Read, &mut [], &mut length)?;
+                length[0] as usize
+            }
+            false => self.0.read_register(Config::PKTLEN)? as usize,
+        };
+        let status_len = 2 * self.0.rx_status_fields as usize;
+        if len > buff.len() || len + status_len > FIFO_SIZE_MAX as usize {
+            self.exit_rx_tx()?;
+            self.await_machine_state(MachineState::IDLE)?;
+            self.flush_rx_fifo_buffer()?;
+            return Err(Error::UserInputError(UserError::ArrayTooLong(len)));
+        }
+
+        let mut status = [0; 2];
+        self.0.access_fifo(Access::Read, &mut buff[..len], &mut status[..status_len])?;
+        let info = match self.0.rx_status_fields {
+            true if status[1] & 0x80 == 0 => return Err(Error::CrcMismatch),
+            true => BasicInfo::new(from_rssi_to_rssi_dbm(status[0]), (status[1] & 0x7F) as u16),
+            false => BasicInfo::new(self.get_rssi_dbm()?, 0),
+        };
+        Ok((len, info))
+    }
+}
+
+impl<SPI, SpiE, O> Rssi for Cc1101<SPI, O>
+where
+    SPI: SpiDevice<u8, Error = SpiE>,
+    O: FnMut(StatusByte),
+    SpiE: Debug,
+{
+    type Error = Error<SpiE>;
+
+    fn poll_rssi(&mut self) -> Result<i16, Self::Error> {
+        self.get_rssi_dbm()
+    }
+}
+
+impl<SPI, SpiE, O> Channel for Cc1101<SPI, O>
+where
+    SPI: SpiDevice<u8, Error = SpiE>,
+    O: FnMut(StatusByte),
+    SpiE: Debug,
+{
+    type Channel = RadioChannel;
+    type Error = Error<SpiE>;
+
+    fn set_channel(&mut self, channel: &Self::Channel) -> Result<(), Self::Error> {
+        match *channel {
+            RadioChannel::Number(number) => Cc1101::set_channel(self, number),
+            RadioChannel::Frequency(hz) => self.set_frequency(hz),
+        }
+    }
+}
+
+impl<SPI, SpiE, O> State for Cc1101<SPI, O>
+where
+    SPI: SpiDevice<u8, Error = SpiE>,
+    O: FnMut(StatusByte),
+    SpiE: Debug,
+{
+    type State = RadioMode;
+    type Error = Error<SpiE>;
+
+    fn set_state(&mut self, state: Self::State) -> Result<(), Self::Error> {
+        match state {
+            RadioMode::Idle if self.0.sleep_context.is_some() => self.wake(false),
+            _ => self.set_radio_mode(state),
+        }
+    }
+
+    fn get_state(&mut self) -> Result<Self::State, Self::Error> {
+        // Reading MARCSTATE would wake the chip up again
+        if self.0.sleep_context.is_some() {
+            return Ok(RadioMode::Sleep);
+        }
+        self.get_machine_state().map(RadioMode::from)
+    }
+}
+
+impl<SPI, SpiE, O> Busy for Cc1101<SPI, O>
+where
+    SPI: SpiDevice<u8, Error = SpiE>,
+    O: FnMut(StatusByte),
+    SpiE: Debug,
+{
+    type Error = Error<SpiE>;
+
+    /// Transmitting, or receiving a packet after its sync word.
+    fn is_busy(&mut self) -> Result<bool, Self::Error> {
+        match self.get_machine_state()? {
+            MachineState::RX => Ok(self.get_packet_status()?.sof_delimiter),
+            state => Ok(RadioMode::from(state) == RadioMode::Transmit),
+        }
+    }
+}
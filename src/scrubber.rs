@@ -0,0 +1,357 @@
+//! Detection and repair of configuration register upsets.
+//!
+//! Single-event upsets may flip bits in the configuration registers and the power amplifier table
+//! of a radio in orbit. The [`Scrubber`] keeps a golden [`ConfigImage`], compares it with the live
+//! registers when [`Scrubber::scrub`] is called, and rewrites the registers that differ.
+
+use crate::lowlevel::access::Access;
+use crate::lowlevel::registers::Config;
+use crate::lowlevel::PATABLE_SIZE;
+use crate::{Cc1101, ConfigImage, Error, MachineState, StatusByte};
+use hal::spi::SpiDevice;
+
+const NUM_COUNTERS: usize = Config::ALL.len() + PATABLE_SIZE;
+
+/// Register checked by the scrubber.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ScrubRegister {
+    /// Configuration register.
+    Config(Config),
+    /// Power amplifier table entry (0..8).
+    PaTable(u8),
+}
+
+impl ScrubRegister {
+    fn index(self) -> usize {
+        match self {
+            ScrubRegister::Config(reg) => reg as usize,
+            ScrubRegister::PaTable(index) => Config::ALL.len() + index as usize,
+        }
+    }
+}
+
+/// Register found to differ from the golden image.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Upset {
+    /// Register holding the corrupted value.
+    pub register: ScrubRegister,
+    /// Value from the golden image.
+    pub expected: u8,
+    /// Value read from the chip.
+    pub found: u8,
+}
+
+/// Configuration scrubber, holding the golden image and per register upset counters.
+pub struct Scrubber {
+    golden: ConfigImage,
+    upsets: [u16; NUM_COUNTERS],
+}
+
+impl Scrubber {
+    /// Create a scrubber from a known good image.
+    pub fn new(golden: ConfigImage) -> Self {
+        Scrubber {
+            golden,
+            upsets: [0; NUM_COUNTERS],
+        }
+    }
+
+    /// Create a scrubber using the current chip configuration as the golden image.
+    pub fn capture<SPI, SpiE, O>(radio: &mut Cc1101<SPI, O>) -> Result<Self, Error<SpiE>>
+    where
+        SPI: SpiDevice<u8, Error = SpiE>,
+        O: FnMut(StatusByte),
+    {
+        Ok(Scrubber::new(radio.read_config_image()?))
+    }
+
+    /// The golden image.
+    pub fn golden(&self) -> &ConfigImage {
+        &self.golden
+    }
+
+    /// Update a register in the golden image, to be used after an intentional reconfiguration.
+    pub fn update_golden(&mut self, reg: Config, value: u8) {
+        self.golden.set(reg, value);
+    }
+
+    /// Number of upsets detected in the given register, saturating at `u16::MAX`.
+    pub fn upsets(&self, register: ScrubRegister) -> u16 {
+        self.upsets[register.index()]
+    }
+
+    /// Total number of upsets detected, in all registers.
+    pub fn total_upsets(&self) -> u32 {
+        self.upsets.iter().map(|&n| n as u32).sum()
+    }
+
+    /// Reset all upset counters.
+    pub fn clear_upsets(&mut self) {
+        self.upsets = [0; NUM_COUNTERS];
+    }
+
+    /// Compare the live registers with the golden image, report every upset and rewrite the
+    /// corrupted registers. Returns the number of upsets found.
+    ///
+    /// The registers are read while the radio stays in its current state. Only when an upset is
+    /// found, the radio is taken to IDLE for the rewrite, acknowledging RXFIFO_OVERFLOW and
+    /// TXFIFO_UNDERFLOW with SFRX and SFTX, and leaving any other state with SIDLE. Both FIFOs are
+    /// then flushed, a packet being received is lost and a transmission in progress is aborted,
+    /// the packet has to be sent again. RX or FSTXON is re-entered after the rewrite, the radio is
+    /// otherwise left in IDLE.
+    /// The calibration results in FSCAL3..FSCAL1 are updated by the chip and are not checked.
+    pub fn scrub<SPI, SpiE, O, F>(
+        &mut self,
+        radio: &mut Cc1101<SPI, O>,
+        mut report: F,
+    ) -> Result<usize, Error<SpiE>>
+    where
+        SPI: SpiDevice<u8, Error = SpiE>,
+        O: FnMut(StatusByte),
+        F: FnMut(Upset),
+    {
+        let live = radio.read_config_image()?;
+        let found = self.compare(&live, &mut report);
+        if found > 0 {
+            self.repair(radio, &live)?;
+        }
+        Ok(found)
+    }
+
+    /// Compare `live` with the golden image, counting and reporting every upset.
+    fn compare<F: FnMut(Upset)>(&mut self, live: &ConfigImage, report: &mut F) -> usize {
+        let mut found = 0;
+
+        let config = Config::ALL
+            .iter()
+            .filter(|reg| !reg.updated_by_chip())
+            .map(|&reg| (ScrubRegister::Config(reg), self.golden.get(reg), live.get(reg)));
+        let patable = (0..PATABLE_SIZE)
+            .map(|i| (ScrubRegister::PaTable(i as u8), self.golden.patable[i], live.patable[i]));
+
+        for (register, expected, found_value) in config.chain(patable) {
+            if expected != found_value {
+                let counter = &mut self.upsets[register.index()];
+                *counter = counter.saturating_add(1);
+                found += 1;
+                report(Upset {
+                    register,
+                    expected,
+                    found: found_value,
+                });
+            }
+        }
+
+        found
+    }
+
+    fn repair<SPI, SpiE, O>(
+        &self,
+        radio: &mut Cc1101<SPI, O>,
+        live: &ConfigImage,
+    ) -> Result<(), Error<SpiE>>
+    where
+        SPI: SpiDevice<u8, Error = SpiE>,
+        O: FnMut(StatusByte),
+    {
+        let state = match radio.get_machine_state() {
+            Ok(state) => Some(state),
+            Err(Error::InvalidState(_)) => None,
+            Err(e) => return Err(e),
+        };
+        // Configuration registers must only be written in IDLE
+        if state != Some(MachineState::IDLE) {
+            match state {
+                // SFRX and SFTX acknowledge the FIFO errors, and leave the radio in IDLE
+                Some(MachineState::RXFIFO_OVERFLOW) => radio.flush_rx_fifo_buffer()?,
+                Some(MachineState::TXFIFO_UNDERFLOW) => radio.flush_tx_fifo_buffer()?,
+                _ => radio.exit_rx_tx()?,
+            }
+            radio.await_machine_state(MachineState::IDLE)?;
+            // Discard what is left of the interrupted packets
+            radio.flush_rx_fifo_buffer()?;
+            radio.flush_tx_fifo_buffer()?;
+        }
+
+        for &reg in Config::ALL.iter().filter(|reg| !reg.updated_by_chip()) {
+            if self.golden.get(reg) != live.get(reg) {
+                // Burst access writes through to the chip, also when the shadow copy is enabled
+                radio.0.write_burst(reg, &[self.golden.get(reg)])?;
+            }
+        }
+        if self.golden.patable != live.patable {
+            let mut patable = self.golden.patable;
+            radio.0.access_patable(Access::Write, &mut patable)?;
+        }
+
+        match state {
+            Some(MachineState::RX) => radio.enable_rx(),
+            Some(MachineState::FSTXON) => radio.enable_and_cal_freq_synth(),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lowlevel::registers::Command;
+    use crate::mock::{radio, Chip};
+
+    fn image() -> ConfigImage {
+        let mut image = ConfigImage {
+            config: [0; Config::ALL.len()],
+            patable: [0xC0, 0, 0, 0, 0, 0, 0, 0],
+        };
+        image.set(Config::FREQ2, 0x10);
+        image.set(Config::FSCAL1, 0x20);
+        image
+    }
+
+    fn compare(scrubber: &mut Scrubber, live: &ConfigImage) -> ([Option<Upset>; 4], usize) {
+        let mut upsets = [None; 4];
+        let mut n = 0;
+        let found = scrubber.compare(live, &mut |upset| {
+            upsets[n] = Some(upset);
+            n += 1;
+        });
+        assert_eq!(found, n);
+        (upsets, found)
+    }
+
+    #[test]
+    fn test_no_upsets() {
+        let mut scrubber = Scrubber::new(image());
+        assert_eq!(compare(&mut scrubber, &image()), ([None; 4], 0));
+        assert_eq!(scrubber.total_upsets(), 0);
+    }
+
+    #[test]
+    fn test_upsets() {
+        let mut scrubber = Scrubber::new(image());
+        let mut live = image();
+        live.set(Config::FREQ2, 0x11);
+        live.patable[0] = 0x40;
+        let (upsets, found) = compare(&mut scrubber, &live);
+        assert_eq!(found, 2);
+        assert_eq!(
+            upsets[..2],
+            [
+                Some(Upset {
+                    register: ScrubRegister::Config(Config::FREQ2),
+                    expected: 0x10,
+                    found: 0x11,
+                }),
+                Some(Upset {
+                    register: ScrubRegister::PaTable(0),
+                    expected: 0xC0,
+                    found: 0x40,
+                }),
+            ]
+        );
+
+        compare(&mut scrubber, &live);
+        assert_eq!(scrubber.upsets(ScrubRegister::Config(Config::FREQ2)), 2);
+        assert_eq!(scrubber.upsets(ScrubRegister::PaTable(0)), 2);
+        assert_eq!(scrubber.total_upsets(), 4);
+        scrubber.clear_upsets();
+        assert_eq!(scrubber.total_upsets(), 0);
+    }
+
+    #[test]
+    fn test_calibration_results_ignored() {
+        let mut scrubber = Scrubber::new(image());
+        let mut live = image();
+        live.set(Config::FSCAL1, 0x21);
+        assert_eq!(compare(&mut scrubber, &live).1, 0);
+
+        scrubber.update_golden(Config::FREQ2, 0x12);
+        assert_eq!(compare(&mut scrubber, &live).1, 1);
+    }
+
+    /// Radio with an upset in FREQ2 and PATABLE, and a scrubber holding the original values.
+    fn upset(state: MachineState) -> (Cc1101<Chip>, Scrubber) {
+        let mut radio = radio();
+        let scrubber = Scrubber::capture(&mut radio).unwrap();
+        let chip = &mut radio.0.spi;
+        chip.config[Config::FREQ2 as usize] ^= 0x01;
+        chip.patable[0] ^= 0x80;
+        chip.state = state;
+        chip.rx_fifo.extend([3, 1]);
+        chip.tx_fifo.extend([5, 1, 2]);
+        chip.strobes.clear();
+        (radio, scrubber)
+    }
+
+    fn scrub(radio: &mut Cc1101<Chip>, scrubber: &mut Scrubber) -> usize {
+        let found = scrubber.scrub(radio, |_| {}).unwrap();
+        let chip = &radio.0.spi;
+        assert_eq!(chip.get(Config::FREQ2), Config::FREQ2.reset_value());
+        assert_eq!(chip.patable, scrubber.golden().patable);
+        assert_eq!(chip.illegal, []);
+        found
+    }
+
+    #[test]
+    fn test_scrub_rx() {
+        let (mut radio, mut scrubber) = upset(MachineState::RX);
+        assert_eq!(scrub(&mut radio, &mut scrubber), 2);
+        let chip = &radio.0.spi;
+        assert_eq!(chip.strobes, [Command::SIDLE, Command::SFRX, Command::SFTX, Command::SRX]);
+        assert!(chip.rx_fifo.is_empty() && chip.tx_fifo.is_empty());
+        assert_eq!(chip.state, MachineState::RX);
+        assert_eq!(scrubber.total_upsets(), 2);
+    }
+
+    #[test]
+    fn test_scrub_tx() {
+        let (mut radio, mut scrubber) = upset(MachineState::TX);
+        assert_eq!(scrub(&mut radio, &mut scrubber), 2);
+        let chip = &radio.0.spi;
+        assert!(chip.tx_fifo.is_empty());
+        assert_eq!(chip.state, MachineState::IDLE);
+    }
+
+    #[test]
+    fn test_scrub_fifo_errors() {
+        let (mut radio, mut scrubber) = upset(MachineState::RXFIFO_OVERFLOW);
+        radio.0.spi.rx_overflow = true;
+        scrub(&mut radio, &mut scrubber);
+        let chip = &radio.0.spi;
+        assert_eq!(chip.strobes, [Command::SFRX, Command::SFRX, Command::SFTX]);
+        assert!(!chip.rx_overflow && chip.rx_fifo.is_empty());
+        assert_eq!(chip.state, MachineState::IDLE);
+
+        let (mut radio, mut scrubber) = upset(MachineState::TXFIFO_UNDERFLOW);
+        radio.0.spi.tx_underflow = true;
+        scrub(&mut radio, &mut scrubber);
+        let chip = &radio.0.spi;
+        assert_eq!(chip.strobes, [Command::SFTX, Command::SFRX, Command::SFTX]);
+        assert!(!chip.tx_underflow && chip.tx_fifo.is_empty());
+        assert_eq!(chip.state, MachineState::IDLE);
+    }
+
+    #[test]
+    fn test_scrub_idle() {
+        let (mut radio, mut scrubber) = upset(MachineState::IDLE);
+        scrub(&mut radio, &mut scrubber);
+        // Nothing interrupted, the FIFOs are kept
+        let chip = &radio.0.spi;
+        assert_eq!(chip.strobes, []);
+        assert_eq!(chip.tx_fifo, [5, 1, 2]);
+    }
+
+    #[test]
+    fn test_scrub_without_upsets() {
+        let (mut radio, mut scrubber) = upset(MachineState::RX);
+        scrubber.update_golden(Config::FREQ2, radio.0.spi.get(Config::FREQ2));
+        radio.0.spi.patable = scrubber.golden().patable;
+        assert_eq!(scrubber.scrub(&mut radio, |_| panic!()), Ok(0));
+        let chip = &radio.0.spi;
+        assert_eq!(chip.strobes, []);
+        assert_eq!(chip.rx_fifo, [3, 1]);
+        assert_eq!(chip.state, MachineState::RX);
+    }
+}
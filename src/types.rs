@@ -1,5 +1,9 @@
+use crate::lowlevel::registers::Config;
+use crate::lowlevel::PATABLE_SIZE;
+
 /// Radio operational mode.
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum RadioMode {
     Idle,
     Sleep,
@@ -10,6 +14,7 @@ pub enum RadioMode {
 
 /// Packet length configuration.
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum PacketLength {
     /// Set packet length to a fixed value.
     Fixed(u8),
@@ -21,6 +26,7 @@ pub enum PacketLength {
 
 /// Address check configuration.
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum AddressFilter {
     /// No address check.
     Disabled,
@@ -33,14 +39,54 @@ pub enum AddressFilter {
 }
 
 /// Sync word configuration.
+///
+/// The carrier sense variants additionally require the RSSI to be above the carrier sense
+/// threshold, see `set_carrier_sense_abs_threshold` and `set_carrier_sense_rel_threshold`.
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum SyncMode {
     /// No sync word.
     Disabled,
     /// Match 15 of 16 bits of given sync word.
     MatchPartial(u16),
-    /// Match 30 of 32 bits of a repetition of given sync word.
+    /// Match 30 of 32 bits of a repetition of given sync word, i.e. `SYNC1:SYNC0:SYNC1:SYNC0`.
     MatchPartialRepeated(u16),
     /// Match 16 of 16 bits of given sync word.
     MatchFull(u16),
+    /// Match 30 of 32 bits of given 32-bit sync word. The chip sends and detects `SYNC1:SYNC0`
+    /// twice, so both halves of the word must be equal, e.g. `0xD391_D391`.
+    MatchPartial32(u32),
+    /// No sync word, carrier sense above threshold.
+    CarrierSense,
+    /// Match 15 of 16 bits of given sync word, carrier sense above threshold.
+    MatchPartialCarrierSense(u16),
+    /// Match 30 of 32 bits of a repetition of given sync word, carrier sense above threshold.
+    MatchPartialRepeatedCarrierSense(u16),
+    /// Match 16 of 16 bits of given sync word, carrier sense above threshold.
+    MatchFullCarrierSense(u16),
+    /// Match 30 of 32 bits of given 32-bit sync word, carrier sense above threshold. Both halves
+    /// of the word must be equal, as for `MatchPartial32`.
+    MatchPartial32CarrierSense(u32),
+}
+
+/// Snapshot of all configuration registers and the power amplifier table.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ConfigImage {
+    /// Configuration registers, indexed by address.
+    pub config: [u8; Config::ALL.len()],
+    /// Power amplifier table.
+    pub patable: [u8; PATABLE_SIZE],
+}
+
+impl ConfigImage {
+    /// Value of a configuration register in the image.
+    pub fn get(&self, reg: Config) -> u8 {
+        self.config[reg as usize]
+    }
+
+    /// Set the value of a configuration register in the image.
+    pub fn set(&mut self, reg: Config, value: u8) {
+        self.config[reg as usize] = value;
+    }
 }
@@ -0,0 +1,378 @@
+//! Radio state encoded in the type.
+//!
+//! [`Cc1101`] wraps the radio and tracks the commanded state of the radio state machine with a
+//! marker type, so that operations only legal in a given state, e.g. configuration writes and FIFO
+//! flushes in IDLE, cannot be called in another state. State transitions consume the radio and
+//! return it in the new state, or hand it back in the previous state on failure.
+//!
+//! After a packet has been received or sent, the chip moves on as configured by MCSM1.RXOFF_MODE
+//! and MCSM1.TXOFF_MODE. The type keeps the state that was entered, leaving it with
+//! [`Cc1101::into_idle`] is always legal.
+//!
+//! ```
+//! use cc1101::typestate::{Cc1101, Idle, Rx, Transition};
+//! use embedded_hal::spi::SpiDevice;
+//!
+//! fn listen<SPI: SpiDevice<u8>>(
+//!     mut radio: Cc1101<SPI, Idle>,
+//! ) -> Transition<SPI, Idle, Rx, SPI::Error> {
+//!     let _ = radio.configure().set_channel(3);
+//!     radio.into_rx()
+//! }
+//! ```
+//!
+//! The radio is only configured in IDLE:
+//!
+//! ```compile_fail
+//! # use cc1101::typestate::{Cc1101, Rx};
+//! # use embedded_hal::spi::SpiDevice;
+//! fn retune<SPI: SpiDevice<u8>>(mut radio: Cc1101<SPI, Rx>) {
+//!     let _ = radio.configure().set_channel(3);
+//! }
+//! ```
+//!
+//! The TX FIFO is only followed while transmitting:
+//!
+//! ```compile_fail
+//! # use cc1101::typestate::{Cc1101, Idle};
+//! # use embedded_hal::spi::SpiDevice;
+//! fn progress<SPI: SpiDevice<u8>>(mut radio: Cc1101<SPI, Idle>) {
+//!     let _ = radio.get_tx_bytes();
+//! }
+//! ```
+//!
+//! The chip is woken up before entering RX:
+//!
+//! ```compile_fail
+//! # use cc1101::typestate::{Cc1101, Sleep};
+//! # use embedded_hal::spi::SpiDevice;
+//! fn listen<SPI: SpiDevice<u8>>(radio: Cc1101<SPI, Sleep>) {
+//!     let _ = radio.into_rx();
+//! }
+//! ```
+
+use crate::lowlevel::types::*;
+use crate::{AddressFilter, ConfigImage, Error, MachineState, PacketLength, StatusByte, SyncMode};
+use core::marker::PhantomData;
+use hal::{digital::PinState, spi::SpiDevice};
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+/// Radio state tracked by the type.
+pub trait State: sealed::Sealed {}
+
+/// IDLE state, the radio may be configured.
+pub struct Idle;
+/// RX state, receiving.
+pub struct Rx;
+/// TX state, transmitting.
+pub struct Tx;
+/// SLEEP state, entered with SPWD, registers not retained are saved.
+pub struct Sleep;
+
+impl sealed::Sealed for Idle {}
+impl sealed::Sealed for Rx {}
+impl sealed::Sealed for Tx {}
+impl sealed::Sealed for Sleep {}
+impl State for Idle {}
+impl State for Rx {}
+impl State for Tx {}
+impl State for Sleep {}
+
+/// Radio in the state `S`.
+pub struct Cc1101<SPI, S: State, O = fn(StatusByte)> {
+    radio: crate::Cc1101<SPI, O>,
+    state: PhantomData<S>,
+}
+
+/// Result of a transition from state `S` to `T`, handing the radio back in `S` on failure.
+pub type Transition<SPI, S, T, SpiE, O = fn(StatusByte)> =
+    Result<Cc1101<SPI, T, O>, (Cc1101<SPI, S, O>, Error<SpiE>)>;
+
+impl<SPI, S: State, O> Cc1101<SPI, S, O> {
+    fn into_state<T: State>(self) -> Cc1101<SPI, T, O> {
+        Cc1101 {
+            radio: self.radio,
+            state: PhantomData,
+        }
+    }
+
+    /// Release the radio, giving up the state tracking.
+    pub fn release(self) -> crate::Cc1101<SPI, O> {
+        self.radio
+    }
+}
+
+impl<SPI, SpiE, S: State, O> Cc1101<SPI, S, O>
+where
+    SPI: SpiDevice<u8, Error = SpiE>,
+    O: FnMut(StatusByte),
+{
+    fn transition<T: State>(
+        mut self,
+        f: impl FnOnce(&mut crate::Cc1101<SPI, O>) -> Result<(), Error<SpiE>>,
+    ) -> Transition<SPI, S, T, SpiE, O> {
+        match f(&mut self.radio) {
+            Ok(()) => Ok(self.into_state()),
+            Err(e) => Err((self, e)),
+        }
+    }
+
+    fn idle(self) -> Transition<SPI, S, Idle, SpiE, O> {
+        self.transition(|radio| {
+            radio.exit_rx_tx()?;
+            radio.await_machine_state(MachineState::IDLE)
+        })
+    }
+}
+
+impl<SPI, SpiE, O> Cc1101<SPI, Idle, O>
+where
+    SPI: SpiDevice<u8, Error = SpiE>,
+    O: FnMut(StatusByte),
+{
+    /// Take ownership of the radio, and bring it to IDLE. The radio is handed back on failure.
+    pub fn new(radio: crate::Cc1101<SPI, O>) -> Result<Self, (crate::Cc1101<SPI, O>, Error<SpiE>)> {
+        let radio: Cc1101<SPI, Idle, O> = Cc1101 {
+            radio,
+            state: PhantomData,
+        };
+        radio.idle().map_err(|(radio, e)| (radio.release(), e))
+    }
+
+    /// Access the configuration of the radio, without the strobes changing its state.
+    pub fn configure(&mut self) -> Configuration<'_, SPI, O> {
+        Configuration {
+            radio: &mut self.radio,
+        }
+    }
+
+    /// Current machine state, IDLE unless the chip went off on its own.
+    pub fn get_machine_state(&mut self) -> Result<MachineState, Error<SpiE>> {
+        self.radio.get_machine_state()
+    }
+
+    /// Command Strobe: Flush the RX FIFO buffer
+    pub fn flush_rx_fifo_buffer(&mut self) -> Result<(), Error<SpiE>> {
+        self.radio.flush_rx_fifo_buffer()
+    }
+
+    /// Command Strobe: Flush the TX FIFO buffer
+    pub fn flush_tx_fifo_buffer(&mut self) -> Result<(), Error<SpiE>> {
+        self.radio.flush_tx_fifo_buffer()
+    }
+
+    /// Command Strobe: Calibrate frequency synthesizer and turn it off, returning to IDLE.
+    pub fn calibrate(&mut self) -> Result<(), Error<SpiE>> {
+        self.radio.cal_freq_synth_and_turn_off()?;
+        self.radio.await_machine_state(MachineState::IDLE)
+    }
+
+    /// Write data into the TX FIFO, to be sent once in TX.
+    pub fn write_data(
+        &mut self,
+        length: &mut Option<u8>,
+        address: &mut Option<u8>,
+        data: &mut [u8],
+    ) -> Result<(), Error<SpiE>> {
+        self.radio.write_data(length, address, data)
+    }
+
+    /// Command Strobe: Enable RX
+    pub fn into_rx(self) -> Transition<SPI, Idle, Rx, SpiE, O> {
+        self.transition(|radio| radio.enable_rx())
+    }
+
+    /// Command Strobe: Enable TX
+    pub fn into_tx(self) -> Transition<SPI, Idle, Tx, SpiE, O> {
+        self.transition(|radio| radio.enable_tx())
+    }
+
+    /// Enter SLEEP, saving the registers which are not retained, see [`crate::Cc1101::sleep`].
+    pub fn into_sleep(self) -> Transition<SPI, Idle, Sleep, SpiE, O> {
+        self.transition(|radio| radio.sleep())
+    }
+}
+
+impl<SPI, SpiE, O> Cc1101<SPI, Rx, O>
+where
+    SPI: SpiDevice<u8, Error = SpiE>,
+    O: FnMut(StatusByte),
+{
+    /// Read number of bytes in RX FIFO
+    pub fn get_rx_bytes(&mut self) -> Result<u8, Error<SpiE>> {
+        self.radio.get_rx_bytes()
+    }
+
+    /// Read data from FIFO
+    pub fn read_data(
+        &mut self,
+        length: &mut Option<u8>,
+        address: &mut Option<u8>,
+        rssi: &mut Option<i16>,
+        lqi: &mut Option<u8>,
+        data: &mut [u8],
+    ) -> Result<(), Error<SpiE>> {
+        self.radio.read_data(length, address, rssi, lqi, data)
+    }
+
+    /// Received Signal Strength Indicator
+    pub fn get_rssi_dbm(&mut self) -> Result<i16, Error<SpiE>> {
+        self.radio.get_rssi_dbm()
+    }
+
+    /// Current machine state, to follow the chip after the end of a packet.
+    pub fn get_machine_state(&mut self) -> Result<MachineState, Error<SpiE>> {
+        self.radio.get_machine_state()
+    }
+
+    /// Command Strobe: Enable TX, if the channel is clear when CCA is enabled.
+    pub fn into_tx(self) -> Transition<SPI, Rx, Tx, SpiE, O> {
+        self.transition(|radio| radio.enable_tx())
+    }
+
+    /// Command Strobe: Exit RX, waiting for IDLE.
+    pub fn into_idle(self) -> Transition<SPI, Rx, Idle, SpiE, O> {
+        self.idle()
+    }
+}
+
+impl<SPI, SpiE, O> Cc1101<SPI, Tx, O>
+where
+    SPI: SpiDevice<u8, Error = SpiE>,
+    O: FnMut(StatusByte),
+{
+    /// Read number of bytes in TX FIFO
+    pub fn get_tx_bytes(&mut self) -> Result<u8, Error<SpiE>> {
+        self.radio.get_tx_bytes()
+    }
+
+    /// Write data into the TX FIFO, e.g. to refill it while transmitting.
+    pub fn write_data(
+        &mut self,
+        length: &mut Option<u8>,
+        address: &mut Option<u8>,
+        data: &mut [u8],
+    ) -> Result<(), Error<SpiE>> {
+        self.radio.write_data(length, address, data)
+    }
+
+    /// Current machine state, to follow the chip after the end of a packet.
+    pub fn get_machine_state(&mut self) -> Result<MachineState, Error<SpiE>> {
+        self.radio.get_machine_state()
+    }
+
+    /// Command Strobe: Enable RX
+    pub fn into_rx(self) -> Transition<SPI, Tx, Rx, SpiE, O> {
+        self.transition(|radio| radio.enable_rx())
+    }
+
+    /// Command Strobe: Exit TX, waiting for IDLE.
+    pub fn into_idle(self) -> Transition<SPI, Tx, Idle, SpiE, O> {
+        self.idle()
+    }
+}
+
+impl<SPI, SpiE, O> Cc1101<SPI, Sleep, O>
+where
+    SPI: SpiDevice<u8, Error = SpiE>,
+    O: FnMut(StatusByte),
+{
+    /// Wake the chip up and restore the registers, see [`crate::Cc1101::wake`].
+    pub fn wake(self, calibrate: bool) -> Transition<SPI, Sleep, Idle, SpiE, O> {
+        self.transition(|radio| radio.wake(calibrate))
+    }
+}
+
+/// Configuration access to a radio in IDLE, see [`Cc1101::configure`]. Only the register
+/// settings of [`crate::Cc1101`] are available, not the strobes changing the radio state.
+pub struct Configuration<'a, SPI, O = fn(StatusByte)> {
+    radio: &'a mut crate::Cc1101<SPI, O>,
+}
+
+macro_rules! delegate {
+    ($(fn $name:ident(&mut self $(, $arg:ident: $ty:ty)*) -> $ret:ty;)*) => {
+        $(
+            #[doc = concat!("See [`crate::Cc1101::", stringify!($name), "`].")]
+            pub fn $name(&mut self $(, $arg: $ty)*) -> Result<$ret, Error<SpiE>> {
+                self.radio.$name($($arg),*)
+            }
+        )*
+    };
+}
+
+impl<SPI, SpiE, O> Configuration<'_, SPI, O>
+where
+    SPI: SpiDevice<u8, Error = SpiE>,
+    O: FnMut(StatusByte),
+{
+    delegate! {
+        fn shadow_registers_enable(&mut self, init: ShadowInit) -> ();
+        fn shadow_registers_disable(&mut self) -> ();
+        fn flush_registers(&mut self) -> ();
+        fn identify_chip(&mut self) -> ChipVariant;
+        fn get_hw_info(&mut self) -> (u8, u8);
+        fn read_config_image(&mut self) -> ConfigImage;
+        fn write_config_image(&mut self, image: &ConfigImage) -> ();
+        fn set_gdo_config(&mut self, pin: GdoPin, config: GdoCfg) -> ();
+        fn set_gdo_active_state(&mut self, pin: GdoPin, state: PinState) -> ();
+        fn set_gdo_drive_strength(&mut self, high_strength: bool) -> ();
+        fn temperature_sensor_enable(&mut self, enable: bool) -> ();
+        fn set_fifo_threshold(&mut self, threshold: FifoThreshold) -> ();
+        fn get_fifo_threshold(&mut self) -> FifoThreshold;
+        fn set_close_in_rx(&mut self, attenuation: CloseInRx) -> ();
+        fn get_close_in_rx(&mut self) -> CloseInRx;
+        fn adc_retention_enable(&mut self, enable: bool) -> ();
+        fn is_adc_retention_enabled(&mut self) -> bool;
+        fn set_frequency(&mut self, hz: u64) -> ();
+        fn set_channel(&mut self, channel: u8) -> ();
+        fn get_channel(&mut self) -> u8;
+        fn get_fs_calibration(&mut self) -> FsCal;
+        fn set_fs_calibration(&mut self, cal: FsCal) -> ();
+        fn set_freq_if(&mut self, hz: u64) -> ();
+        fn set_magn_target(&mut self, target: TargetAmplitude) -> ();
+        fn set_filter_length(&mut self, filter_length: FilterLength) -> ();
+        fn set_autocalibration(&mut self, autocal: AutoCalibration) -> ();
+        fn get_autocalibration(&mut self) -> AutoCalibration;
+        fn set_power_on_timeout(&mut self, timeout: PoTimeout) -> ();
+        fn get_power_on_timeout(&mut self) -> PoTimeout;
+        fn pin_ctrl_enable(&mut self, enable: bool) -> ();
+        fn is_pin_ctrl_enabled(&mut self) -> bool;
+        fn xosc_force_on_enable(&mut self, enable: bool) -> ();
+        fn is_xosc_force_on_enabled(&mut self) -> bool;
+        fn set_deviation(&mut self, deviation: u64) -> ();
+        fn set_data_rate(&mut self, baud: u64) -> ();
+        fn fec_enable(&mut self, enable: bool) -> ();
+        fn manchester_enable(&mut self, enable: bool) -> ();
+        fn set_num_preamble(&mut self, num_preamble: NumPreamble) -> ();
+        fn set_rxoff_mode(&mut self, mode: RxOffMode) -> ();
+        fn get_rxoff_mode(&mut self) -> RxOffMode;
+        fn set_txoff_mode(&mut self, mode: TxOffMode) -> ();
+        fn get_txoff_mode(&mut self) -> TxOffMode;
+        fn set_cca_mode(&mut self, cca_mode: CcaMode) -> ();
+        fn set_chanbw(&mut self, bandwidth: u64) -> ();
+        fn set_sync_mode(&mut self, sync_mode: SyncMode) -> ();
+        fn set_carrier_sense_abs_threshold(&mut self, db: Option<i8>) -> ();
+        fn set_carrier_sense_rel_threshold(&mut self, threshold: CarrierSenseRelThr) -> ();
+        fn set_modulation_format(&mut self, mod_format: ModulationFormat) -> ();
+        fn set_preamble_quality_threshold(&mut self, pqt: u8) -> ();
+        fn get_preamble_quality_threshold(&mut self) -> u8;
+        fn crc_autoflush_enable(&mut self, enable: bool) -> ();
+        fn append_status_enable(&mut self, enable: bool) -> ();
+        fn set_address_filter(&mut self, filter: AddressFilter) -> ();
+        fn white_data_enable(&mut self, enable: bool) -> ();
+        fn crc_enable(&mut self, enable: bool) -> ();
+        fn set_packet_format(&mut self, format: PacketFormat) -> ();
+        fn get_packet_format(&mut self) -> PacketFormat;
+        fn set_asynchronous_serial_mode(&mut self, data_out: GdoPin) -> ();
+        fn set_synchronous_serial_mode(
+            &mut self,
+            clock: GdoPin,
+            data_out: GdoPin,
+            sync_mode: SyncMode
+        ) -> ();
+        fn set_packet_length(&mut self, length: PacketLength) -> ();
+    }
+}
@@ -0,0 +1,361 @@
+//! Supervision of the radio state machine.
+//!
+//! The [`Watchdog`] is polled periodically with a millisecond timestamp. It reads MARCSTATE, keeps
+//! track of the time spent in each [`MachineState`], and recovers a chip that stays too long in a
+//! fault, calibration or transitional state. Recovery escalates from SIDLE and flushing the FIFOs
+//! to a chip reset followed by a full reconfiguration from the stored [`ConfigImage`]. A chip that
+//! does not reach IDLE for the flush is reset right away.
+
+use crate::{Cc1101, ConfigImage, Error, MachineState, StatusByte};
+use hal::spi::SpiDevice;
+
+const NUM_STATES: usize = MachineState::TXFIFO_UNDERFLOW as usize + 1;
+
+/// Maximum time (in milliseconds) the chip may stay in a state class, `None` disables the check.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct WatchdogLimits {
+    /// RXFIFO_OVERFLOW and TXFIFO_UNDERFLOW.
+    pub fifo_error_ms: Option<u32>,
+    /// Frequency synthesizer start-up and calibration states, MANCAL to ENDCAL.
+    pub calibration_ms: Option<u32>,
+    /// RX_END, RX_RST, TXRX_SWITCH, RXTX_SWITCH and TX_END.
+    pub transition_ms: Option<u32>,
+    /// TX, e.g. to catch a transmission that never ends.
+    pub tx_ms: Option<u32>,
+    /// Consecutive invalid MARCSTATE readings.
+    pub invalid_ms: Option<u32>,
+    /// A new limit violation within this time after a flush escalates to a chip reset.
+    pub escalation_window_ms: u32,
+}
+
+impl Default for WatchdogLimits {
+    fn default() -> Self {
+        WatchdogLimits {
+            fifo_error_ms: Some(10),
+            calibration_ms: Some(10),
+            transition_ms: Some(10),
+            tx_ms: None,
+            invalid_ms: Some(10),
+            escalation_window_ms: 1000,
+        }
+    }
+}
+
+impl WatchdogLimits {
+    fn limit(&self, state: Option<MachineState>) -> Option<u32> {
+        match state {
+            None => self.invalid_ms,
+            Some(MachineState::RXFIFO_OVERFLOW | MachineState::TXFIFO_UNDERFLOW) => {
+                self.fifo_error_ms
+            }
+            Some(
+                MachineState::VCOON_MC
+                | MachineState::REGON_MC
+                | MachineState::MANCAL
+                | MachineState::VCOON
+                | MachineState::REGON
+                | MachineState::STARTCAL
+                | MachineState::BWBOOST
+                | MachineState::FS_LOCK
+                | MachineState::IFADCON
+                | MachineState::ENDCAL,
+            ) => self.calibration_ms,
+            Some(
+                MachineState::RX_END
+                | MachineState::RX_RST
+                | MachineState::TXRX_SWITCH
+                | MachineState::RXTX_SWITCH
+                | MachineState::TX_END,
+            ) => self.transition_ms,
+            Some(MachineState::TX) => self.tx_ms,
+            Some(_) => None,
+        }
+    }
+}
+
+/// State the chip was stuck in.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum StuckState {
+    /// Valid machine state.
+    Known(MachineState),
+    /// Invalid MARCSTATE value.
+    Invalid(u8),
+}
+
+/// Recovery performed by the watchdog.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum WatchdogEvent {
+    /// SIDLE strobe, RX and TX FIFOs flushed.
+    Flushed {
+        stuck: StuckState,
+        elapsed_ms: u32,
+    },
+    /// Chip reset with SRES and reconfigured from the stored image, radio left in IDLE.
+    Reset {
+        stuck: StuckState,
+        elapsed_ms: u32,
+    },
+}
+
+/// Radio state machine watchdog.
+pub struct Watchdog {
+    config: ConfigImage,
+    limits: WatchdogLimits,
+    current: Option<StuckState>,
+    entered_ms: u32,
+    last_poll_ms: u32,
+    last_flush_ms: Option<u32>,
+    time_in_state: [u32; NUM_STATES],
+}
+
+impl Watchdog {
+    /// Create a watchdog, `config` is written to the chip after a reset.
+    pub fn new(config: ConfigImage, limits: WatchdogLimits) -> Self {
+        Watchdog {
+            config,
+            limits,
+            current: None,
+            entered_ms: 0,
+            last_poll_ms: 0,
+            last_flush_ms: None,
+            time_in_state: [0; NUM_STATES],
+        }
+    }
+
+    /// Replace the configuration written to the chip after a reset.
+    pub fn set_config(&mut self, config: ConfigImage) {
+        self.config = config;
+    }
+
+    /// Accumulated time (in milliseconds) observed in the given state.
+    pub fn time_in_state(&self, state: MachineState) -> u32 {
+        self.time_in_state[state as usize]
+    }
+
+    /// Read the machine state, and recover the chip if it exceeded the limit for that state.
+    pub fn poll<SPI, SpiE, O>(
+        &mut self,
+        radio: &mut Cc1101<SPI, O>,
+        now_ms: u32,
+    ) -> Result<Option<WatchdogEvent>, Error<SpiE>>
+    where
+        SPI: SpiDevice<u8, Error = SpiE>,
+        O: FnMut(StatusByte),
+    {
+        let state = match radio.get_machine_state() {
+            Ok(state) => StuckState::Known(state),
+            Err(Error::InvalidState(value)) => StuckState::Invalid(value),
+            Err(e) => return Err(e),
+        };
+
+        match self.observe(state, now_ms) {
+            Some(elapsed_ms) => self.recover(radio, state, elapsed_ms, now_ms).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Record `state` read at `now_ms`, returns the time spent in it once it exceeds its limit.
+    fn observe(&mut self, state: StuckState, now_ms: u32) -> Option<u32> {
+        if let Some(StuckState::Known(previous)) = self.current {
+            let delta = now_ms.wrapping_sub(self.last_poll_ms);
+            let total = &mut self.time_in_state[previous as usize];
+            *total = total.saturating_add(delta);
+        }
+        self.last_poll_ms = now_ms;
+
+        let same = match (self.current, state) {
+            (Some(StuckState::Invalid(_)), StuckState::Invalid(_)) => true,
+            (current, state) => current == Some(state),
+        };
+        if !same {
+            self.current = Some(state);
+            self.entered_ms = now_ms;
+            return None;
+        }
+
+        let known = match state {
+            StuckState::Known(state) => Some(state),
+            StuckState::Invalid(_) => None,
+        };
+        let elapsed_ms = now_ms.wrapping_sub(self.entered_ms);
+        match self.limits.limit(known) {
+            Some(limit) if elapsed_ms > limit => Some(elapsed_ms),
+            _ => None,
+        }
+    }
+
+    /// Start a recovery at `now_ms`, returns whether it escalates to a chip reset because of a
+    /// flush within the escalation window.
+    fn escalate(&mut self, now_ms: u32) -> bool {
+        self.current = None;
+
+        let escalate = match self.last_flush_ms {
+            Some(flushed) => now_ms.wrapping_sub(flushed) <= self.limits.escalation_window_ms,
+            None => false,
+        };
+        self.last_flush_ms = match escalate {
+            true => None,
+            false => Some(now_ms),
+        };
+        escalate
+    }
+
+    fn recover<SPI, SpiE, O>(
+        &mut self,
+        radio: &mut Cc1101<SPI, O>,
+        stuck: StuckState,
+        elapsed_ms: u32,
+        now_ms: u32,
+    ) -> Result<WatchdogEvent, Error<SpiE>>
+    where
+        SPI: SpiDevice<u8, Error = SpiE>,
+        O: FnMut(StatusByte),
+    {
+        if !self.escalate(now_ms) {
+            match Self::flush(radio) {
+                Ok(()) => {
+                    return Ok(WatchdogEvent::Flushed {
+                        stuck,
+                        elapsed_ms,
+                    })
+                }
+                // The chip does not even reach IDLE, reset it right away
+                Err(Error::Timeout) => self.last_flush_ms = None,
+                Err(e) => return Err(e),
+            }
+        }
+
+        radio.reset_chip()?;
+        // Wait for CHIP_RDYn before reconfiguring, bounded in case the chip stays unresponsive
+        let mut ready = false;
+        for _ in 0..u8::MAX {
+            radio.no_operation()?;
+            if radio.get_chip_status().is_some_and(|status| status.chip_rdy) {
+                ready = true;
+                break;
+            }
+        }
+        if !ready {
+            return Err(Error::ChipNotReady);
+        }
+        radio.write_config_image(&self.config)?;
+        Ok(WatchdogEvent::Reset {
+            stuck,
+            elapsed_ms,
+        })
+    }
+
+    fn flush<SPI, SpiE, O>(radio: &mut Cc1101<SPI, O>) -> Result<(), Error<SpiE>>
+    where
+        SPI: SpiDevice<u8, Error = SpiE>,
+        O: FnMut(StatusByte),
+    {
+        // SFRX and SFTX are only allowed in IDLE
+        radio.exit_rx_tx()?;
+        radio.await_machine_state(MachineState::IDLE)?;
+        radio.flush_rx_fifo_buffer()?;
+        radio.flush_tx_fifo_buffer()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lowlevel::registers::{Command, Config};
+    use crate::mock::{radio, Chip};
+
+    fn watchdog() -> Watchdog {
+        let config = ConfigImage {
+            config: [0; crate::lowlevel::registers::Config::ALL.len()],
+            patable: [0; crate::PATABLE_SIZE],
+        };
+        Watchdog::new(config, WatchdogLimits::default())
+    }
+
+    #[test]
+    fn test_limit_exceeded() {
+        let mut watchdog = watchdog();
+        let overflow = StuckState::Known(MachineState::RXFIFO_OVERFLOW);
+        assert_eq!(watchdog.observe(StuckState::Known(MachineState::RX), 0), None);
+        assert_eq!(watchdog.observe(StuckState::Known(MachineState::RX), 5), None);
+        assert_eq!(watchdog.observe(overflow, 6), None);
+        assert_eq!(watchdog.observe(overflow, 16), None);
+        assert_eq!(watchdog.observe(overflow, 17), Some(11));
+
+        assert_eq!(watchdog.time_in_state(MachineState::RX), 6);
+        assert_eq!(watchdog.time_in_state(MachineState::RXFIFO_OVERFLOW), 11);
+    }
+
+    #[test]
+    fn test_invalid_states_are_one_class() {
+        let mut watchdog = watchdog();
+        assert_eq!(watchdog.observe(StuckState::Invalid(0x20), u32::MAX - 5), None);
+        assert_eq!(watchdog.observe(StuckState::Invalid(0x1F), 5), Some(11));
+    }
+
+    #[test]
+    fn test_escalation() {
+        let mut watchdog = watchdog();
+        assert!(!watchdog.escalate(0));
+        assert!(watchdog.escalate(1000));
+        assert!(!watchdog.escalate(1001));
+        assert!(!watchdog.escalate(2002));
+    }
+
+    /// Radio stuck in RXFIFO_OVERFLOW, and a watchdog that has seen it for 11 ms.
+    fn overflow() -> (Cc1101<Chip>, Watchdog) {
+        let mut radio = radio();
+        radio.set_poll_limit(10);
+        let mut config = radio.read_config_image().unwrap();
+        config.set(Config::CHANNR, 7);
+        let mut watchdog = Watchdog::new(config, WatchdogLimits::default());
+
+        radio.0.spi.state = MachineState::RXFIFO_OVERFLOW;
+        radio.0.spi.rx_overflow = true;
+        assert_eq!(watchdog.poll(&mut radio, 0), Ok(None));
+        radio.0.spi.strobes.clear();
+        (radio, watchdog)
+    }
+
+    #[test]
+    fn test_flush() {
+        let (mut radio, mut watchdog) = overflow();
+        let stuck = StuckState::Known(MachineState::RXFIFO_OVERFLOW);
+        assert_eq!(
+            watchdog.poll(&mut radio, 11),
+            Ok(Some(WatchdogEvent::Flushed {
+                stuck,
+                elapsed_ms: 11,
+            }))
+        );
+        let chip = &radio.0.spi;
+        assert_eq!(chip.strobes, [Command::SIDLE, Command::SFRX, Command::SFTX]);
+        assert_eq!(chip.illegal, []);
+        assert_eq!(chip.state, MachineState::IDLE);
+        assert!(!chip.rx_overflow);
+    }
+
+    #[test]
+    fn test_flush_timeout_resets() {
+        let (mut radio, mut watchdog) = overflow();
+        radio.0.spi.ignore_sidle = true;
+        let stuck = StuckState::Known(MachineState::RXFIFO_OVERFLOW);
+        assert_eq!(
+            watchdog.poll(&mut radio, 11),
+            Ok(Some(WatchdogEvent::Reset {
+                stuck,
+                elapsed_ms: 11,
+            }))
+        );
+        let chip = &radio.0.spi;
+        assert_eq!(chip.strobes[..2], [Command::SIDLE, Command::SRES]);
+        assert_eq!(chip.state, MachineState::IDLE);
+        assert_eq!(chip.get(Config::CHANNR), 7);
+
+        // The reset starts a new escalation window
+        assert!(!watchdog.escalate(12));
+    }
+}